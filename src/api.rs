@@ -0,0 +1,207 @@
+// library api, used by the command line tool, the Python module and other crates
+//
+//   let config = ScoringConfig::from_env()?;
+//   let report = metricslib::score_package("https://github.com/owner/repo", &config)?;
+//
+// Reports are not rounded and carry the explanation of every metric.
+use crate::http::{self, Transport};
+use crate::metrics::curve::Curves;
+use crate::metrics::facts::{Collector, RepositoryFacts};
+use crate::metrics::github::{Github, GithubConfig};
+use crate::metrics::identity::PackageIdentity;
+use crate::metrics::npm::Npm;
+use crate::metrics::profile::{ProfileError, Profiles, Weights};
+use crate::metrics::registry::Registry;
+use crate::metrics::report::ScoreReport;
+use crate::metrics::scoring::Scorer;
+use crate::metrics::{MetricError, Result, Scores};
+use crate::pool;
+use log::info;
+use std::sync::Arc;
+
+// how packages are scored
+#[derive(Debug, Clone)]
+pub struct ScoringConfig {
+    // net score weights of the profile
+    pub weights: Weights,
+    pub curves: Curves,
+    pub registry: Registry,
+    pub github: GithubConfig,
+    // shared by every package, with its response cache and API budget
+    pub transport: Arc<dyn Transport>,
+    // packages scored concurrently by score_many
+    pub workers: usize,
+}
+
+impl ScoringConfig {
+    // default profile of METRICS_CONFIG, GitHub endpoints and workers from the environment
+    pub fn from_env() -> std::result::Result<ScoringConfig, ProfileError> {
+        ScoringConfig::load(None, None)
+    }
+
+    // named profile of the config file, METRICS_CONFIG if no file is given
+    pub fn load(
+        config: Option<&str>,
+        profile: Option<&str>,
+    ) -> std::result::Result<ScoringConfig, ProfileError> {
+        let profiles = match config {
+            Some(path) => Profiles::load(path),
+            None => Profiles::from_env(),
+        }?;
+        let weights = profiles.get(profile)?;
+        let registry = Registry::default();
+        registry
+            .check(&weights)
+            .map_err(|reason| ProfileError::Invalid {
+                profile: profile.unwrap_or(&profiles.default_profile).to_string(),
+                reason,
+            })?;
+        Ok(ScoringConfig {
+            weights,
+            curves: profiles.curves,
+            registry,
+            github: GithubConfig::from_env(),
+            transport: http::transport_from_env(),
+            workers: pool::workers_from_env(),
+        })
+    }
+}
+
+// collector of a GitHub repository or npm package url
+pub fn collector(
+    url: &str,
+    github: &GithubConfig,
+    transport: &Arc<dyn Transport>,
+) -> Result<Box<dyn Collector>> {
+    info!("exploring {}", url);
+
+    // if type is github or npm
+    let u = reqwest::Url::parse(url)
+        .map_err(|_| MetricError::Unsupported(format!("{} is not a url", url)))?;
+    // if github
+    if github.accepts(&u) {
+        Ok(Box::new(Github::with_transport(
+            url,
+            github.clone(),
+            transport.clone(),
+        )?))
+    } else if u.host_str() == Some("www.npmjs.com") {
+        Ok(Box::new(Npm::with_transport(
+            url,
+            github.clone(),
+            transport.clone(),
+        )?))
+    } else {
+        Err(MetricError::Unsupported(format!(
+            "{} is neither a GitHub repository nor an npm package",
+            url
+        )))
+    }
+}
+
+// published name and version of the package at url, see identity.rs
+pub fn package_identity(url: &str, config: &ScoringConfig) -> Result<PackageIdentity> {
+    collector(url, &config.github, &config.transport)?.identity()
+}
+
+// scores of one url
+pub fn score_package(url: &str, config: &ScoringConfig) -> Result<ScoreReport> {
+    let facts = collector(url, &config.github, &config.transport)?.collect()?;
+    score_facts(url, facts, config)
+}
+
+// scores of the facts collected from one url, no network access
+pub fn score_facts(
+    url: &str,
+    facts: RepositoryFacts,
+    config: &ScoringConfig,
+) -> Result<ScoreReport> {
+    info!("calculating score");
+    let project = Scorer::new(facts.clone()).with_curves(config.curves.clone());
+    let scores = Scores::calculate(&project, &facts, &config.registry)?;
+    Ok(ScoreReport::new(
+        url,
+        &scores,
+        &config.weights,
+        &config.registry,
+    ))
+}
+
+// scores of every url on `config.workers` threads, results keep the order of urls
+pub fn score_many(urls: &[String], config: &ScoringConfig) -> Vec<(String, Result<ScoreReport>)> {
+    pool::parallel_map(urls.to_vec(), config.workers, |url| {
+        let result = score_package(&url, config);
+        (url, result)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::tests::StubTransport;
+    use crate::metrics::github::{facts_response, offline_github};
+
+    #[test]
+    fn unsupported_urls() {
+        let config = ScoringConfig::load(None, None).unwrap();
+        let results = score_many(
+            &[
+                "not a url".to_string(),
+                "https://gitlab.com/owner/repo".to_string(),
+            ],
+            &config,
+        );
+        assert_eq!("not a url", results[0].0);
+        assert!(matches!(results[0].1, Err(MetricError::Unsupported(_))));
+        assert!(matches!(results[1].1, Err(MetricError::Unsupported(_))));
+    }
+
+    #[test]
+    fn facts_scored_with_profile() {
+        let facts = offline_github(&facts_response(serde_json::json!({
+            "licenseInfo": { "spdxId": "MIT" }
+        })))
+        .collect()
+        .unwrap();
+        let mut config = ScoringConfig::load(None, None).unwrap();
+        config.weights = Weights(std::collections::BTreeMap::from([(
+            "license".to_string(),
+            1.0,
+        )]));
+        let report = score_facts("https://github.com/owner/repo", facts, &config).unwrap();
+        assert_eq!(1.0, report.net_score);
+        assert_eq!(Some(1.0), report.score("LICENSE_SCORE"));
+        assert!(report.explanation.is_some());
+    }
+
+    #[test]
+    fn configured_transport() {
+        // npm packages and their repositories are fetched through the config, not the environment
+        let stub = StubTransport::default()
+            .with(
+                "https://registry.npmjs.org/left-pad",
+                200,
+                "{\"repository\":{\"url\":\"git+https://github.com/owner/repo.git\"}}",
+            )
+            .with(
+                "https://api.github.com/graphql",
+                200,
+                &facts_response(serde_json::json!({ "licenseInfo": { "spdxId": "MIT" } })),
+            );
+        let mut config = ScoringConfig::load(None, None).unwrap();
+        config.github = GithubConfig::for_host("github.com");
+        config.transport = Arc::new(stub);
+        let report = score_package("https://www.npmjs.com/package/left-pad", &config).unwrap();
+        assert_eq!(Some(1.0), report.score("LICENSE_SCORE"));
+        let identity = package_identity("https://www.npmjs.com/package/left-pad", &config).unwrap();
+        assert_eq!("repo", identity.name);
+    }
+
+    #[test]
+    fn unknown_profile() {
+        assert!(matches!(
+            ScoringConfig::load(None, Some("missing")),
+            Err(ProfileError::UnknownProfile(_))
+        ));
+    }
+}
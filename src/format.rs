@@ -0,0 +1,335 @@
+// output formats of the scoring commands, selected with --format
+//
+// Columns are URL, NET_SCORE, COVERAGE and the metrics in registry order.
+// Unknown scores are null in JSON, empty in CSV and n/a in the text formats.
+// Explanations are written by the JSON formats with --explain and always by html.
+use clap::ValueEnum;
+use metricslib::metrics::registry::Registry;
+use metricslib::ScoreReport;
+use std::io::{self, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    // one JSON object per line
+    Ndjson,
+    // JSON array
+    Json,
+    Csv,
+    Markdown,
+    // standalone page with a bar per score and the explanations
+    Html,
+    // aligned columns for the terminal
+    Table,
+}
+
+impl Format {
+    // whether the format writes explanations without --explain
+    pub fn explains(self) -> bool {
+        self == Format::Html
+    }
+}
+
+pub fn write(
+    out: &mut dyn Write,
+    format: Format,
+    reports: &[ScoreReport],
+    registry: &Registry,
+) -> io::Result<()> {
+    match format {
+        Format::Ndjson => {
+            for report in reports {
+                writeln!(out, "{}", serde_json::to_string(report)?)?;
+            }
+            Ok(())
+        }
+        Format::Json => writeln!(out, "{}", serde_json::to_string_pretty(reports)?),
+        Format::Csv => write_csv(out, reports, registry),
+        Format::Markdown => write_markdown(out, reports, registry),
+        Format::Html => write_html(out, reports, registry),
+        Format::Table => write_table(out, reports, registry),
+    }
+}
+
+fn header(registry: &Registry) -> Vec<String> {
+    let mut header = vec![
+        "URL".to_string(),
+        "NET_SCORE".to_string(),
+        "COVERAGE".to_string(),
+    ];
+    header.extend(registry.metrics().iter().map(|m| m.key.clone()));
+    header
+}
+
+// scores of a report in column order after the url
+fn scores(report: &ScoreReport, registry: &Registry) -> Vec<Option<f64>> {
+    let mut scores = vec![Some(report.net_score), Some(report.coverage)];
+    scores.extend(
+        registry
+            .metrics()
+            .iter()
+            .map(|m| report.metrics.get(&m.key).copied().flatten()),
+    );
+    scores
+}
+
+fn text(score: Option<f64>) -> String {
+    match score {
+        Some(v) => format!("{:.2}", v),
+        None => "n/a".to_string(),
+    }
+}
+
+// quoted if it contains a separator, quote or line break
+// see: https://www.rfc-editor.org/rfc/rfc4180
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn write_csv(out: &mut dyn Write, reports: &[ScoreReport], registry: &Registry) -> io::Result<()> {
+    writeln!(out, "{}", header(registry).join(","))?;
+    for report in reports {
+        let mut row = vec![csv_field(&report.url)];
+        row.extend(
+            scores(report, registry)
+                .into_iter()
+                .map(|s| s.map(|v| format!("{:.2}", v)).unwrap_or_default()),
+        );
+        writeln!(out, "{}", row.join(","))?;
+    }
+    Ok(())
+}
+
+fn write_markdown(
+    out: &mut dyn Write,
+    reports: &[ScoreReport],
+    registry: &Registry,
+) -> io::Result<()> {
+    let header = header(registry);
+    writeln!(out, "| {} |", header.join(" | "))?;
+    let align: Vec<&str> = header
+        .iter()
+        .enumerate()
+        .map(|(i, _)| if i == 0 { "---" } else { "---:" })
+        .collect();
+    writeln!(out, "| {} |", align.join(" | "))?;
+    for report in reports {
+        let mut row = vec![report.url.replace('|', "\\|")];
+        row.extend(scores(report, registry).into_iter().map(text));
+        writeln!(out, "| {} |", row.join(" | "))?;
+    }
+    Ok(())
+}
+
+fn write_table(
+    out: &mut dyn Write,
+    reports: &[ScoreReport],
+    registry: &Registry,
+) -> io::Result<()> {
+    let mut rows = vec![header(registry)];
+    for report in reports {
+        let mut row = vec![report.url.clone()];
+        row.extend(scores(report, registry).into_iter().map(text));
+        rows.push(row);
+    }
+    let widths: Vec<usize> = (0..rows[0].len())
+        .map(|i| rows.iter().map(|r| r[i].chars().count()).max().unwrap_or(0))
+        .collect();
+    for row in rows {
+        // url left aligned, scores right aligned
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(i, (cell, w))| {
+                if i == 0 {
+                    format!("{:<w$}", cell, w = w)
+                } else {
+                    format!("{:>w$}", cell, w = w)
+                }
+            })
+            .collect();
+        writeln!(out, "{}", cells.join("  ").trim_end())?;
+    }
+    Ok(())
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// score with a bar as wide as the score
+fn html_bar(score: Option<f64>) -> String {
+    match score {
+        Some(v) => format!(
+            "<div class=\"bar\"><div style=\"width:{:.0}%\"></div></div>{:.2}",
+            v.clamp(0.0, 1.0) * 100.0,
+            v
+        ),
+        None => "<span class=\"unknown\">n/a</span>".to_string(),
+    }
+}
+
+const HTML_STYLE: &str = "body{font-family:sans-serif}\
+table{border-collapse:collapse}\
+th,td{padding:4px 8px;border-bottom:1px solid #ddd;text-align:left;vertical-align:top}\
+.bar{display:inline-block;width:60px;height:8px;margin-right:6px;background:#eee}\
+.bar div{height:100%;background:#4a90d9}\
+.unknown{color:#999}";
+
+fn write_html(out: &mut dyn Write, reports: &[ScoreReport], registry: &Registry) -> io::Result<()> {
+    let header = header(registry);
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(
+        out,
+        "<html><head><meta charset=\"utf-8\"><title>Package scores</title><style>{}</style></head><body>",
+        HTML_STYLE
+    )?;
+    writeln!(out, "<table>")?;
+    let cells: Vec<String> = header
+        .iter()
+        .map(|h| format!("<th>{}</th>", escape_html(h)))
+        .collect();
+    writeln!(out, "<thead><tr>{}</tr></thead><tbody>", cells.concat())?;
+
+    for report in reports {
+        let url = escape_html(&report.url);
+        let mut cells = vec![format!("<td><a href=\"{}\">{}</a></td>", url, url)];
+        cells.extend(
+            scores(report, registry)
+                .into_iter()
+                .map(|s| format!("<td>{}</td>", html_bar(s))),
+        );
+        writeln!(out, "<tr>{}</tr>", cells.concat())?;
+
+        // explanation of every metric under the scores
+        if let Some(explanation) = &report.explanation {
+            let items: Vec<String> = registry
+                .metrics()
+                .iter()
+                .filter_map(|m| {
+                    let text = explanation[&m.id]["explanation"].as_str()?;
+                    Some(format!(
+                        "<li><b>{}</b>: {}</li>",
+                        escape_html(&m.id),
+                        escape_html(text)
+                    ))
+                })
+                .collect();
+            writeln!(
+                out,
+                "<tr><td colspan=\"{}\"><details><summary>Explanation</summary><ul>{}</ul></details></td></tr>",
+                header.len(),
+                items.concat()
+            )?;
+        }
+    }
+    writeln!(out, "</tbody></table>")?;
+    writeln!(out, "</body></html>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn reports() -> Vec<ScoreReport> {
+        let metrics: BTreeMap<String, Option<f64>> = Registry::default()
+            .metrics()
+            .iter()
+            .map(|m| (m.key.clone(), Some(0.5)))
+            .collect();
+        let mut unknown = metrics.clone();
+        unknown.insert("LICENSE_SCORE".to_string(), None);
+        vec![
+            ScoreReport {
+                url: "https://github.com/owner/repo".to_string(),
+                net_score: 0.5,
+                coverage: 1.0,
+                metrics,
+                explanation: Some(serde_json::json!({
+                    "license": { "explanation": "MIT is compatible with <LGPLv2.1>" }
+                })),
+            },
+            ScoreReport {
+                url: "https://example.com/a,\"b\"".to_string(),
+                net_score: 0.25,
+                coverage: 0.67,
+                metrics: unknown,
+                explanation: None,
+            },
+        ]
+    }
+
+    fn written(format: Format) -> String {
+        let mut out = Vec::new();
+        write(&mut out, format, &reports(), &Registry::default()).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn json() {
+        let ndjson = written(Format::Ndjson);
+        assert_eq!(2, ndjson.lines().count());
+        let parsed: ScoreReport = serde_json::from_str(ndjson.lines().next().unwrap()).unwrap();
+        assert_eq!(reports()[0], parsed);
+
+        let array: Vec<ScoreReport> = serde_json::from_str(&written(Format::Json)).unwrap();
+        assert_eq!(reports(), array);
+    }
+
+    #[test]
+    fn csv() {
+        let csv = written(Format::Csv);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            "URL,NET_SCORE,COVERAGE,RAMP_UP_SCORE,CORRECTNESS_SCORE,BUS_FACTOR_SCORE,\
+             REVIEWED_CODE_SCORE,RESPONSIVE_MAINTAINER_SCORE,PINNING_PRACTICE_SCORE,LICENSE_SCORE",
+            lines[0]
+        );
+        assert_eq!(
+            "https://github.com/owner/repo,0.50,1.00,0.50,0.50,0.50,0.50,0.50,0.50,0.50",
+            lines[1]
+        );
+        // quoted url, unknown license score is empty
+        assert!(lines[2].starts_with("\"https://example.com/a,\"\"b\"\"\",0.25,0.67,"));
+        assert!(lines[2].ends_with(",0.50,"));
+    }
+
+    #[test]
+    fn markdown_and_table() {
+        let markdown = written(Format::Markdown);
+        let lines: Vec<&str> = markdown.lines().collect();
+        assert!(lines[0].starts_with("| URL | NET_SCORE | COVERAGE |"));
+        assert!(lines[1].starts_with("| --- | ---: |"));
+        assert!(lines[3].ends_with("| 0.50 | n/a |"));
+
+        let table = written(Format::Table);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(3, lines.len());
+        assert!(lines[1].starts_with("https://github.com/owner/repo "));
+        // scores are right aligned under their column
+        assert_eq!(
+            lines[0].find("NET_SCORE").unwrap() + "NET_SCORE".len(),
+            lines[1].find("0.50").unwrap() + "0.50".len()
+        );
+    }
+
+    #[test]
+    fn html() {
+        let html = written(Format::Html);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<div style=\"width:50%\"></div>"));
+        assert!(html.contains("<span class=\"unknown\">n/a</span>"));
+        assert!(html.contains("<b>license</b>: MIT is compatible with &lt;LGPLv2.1&gt;"));
+        assert!(html.contains("a,&quot;b&quot;"));
+        // one explanation, the second report has none
+        assert_eq!(1, html.matches("<details>").count());
+    }
+}
@@ -0,0 +1,536 @@
+// HTTP transport shared by the GitHub and npm clients
+//
+// Every API call goes through a `Transport`, so the network can be swapped out:
+//   live    send requests with reqwest (default)
+//   record  send requests with reqwest and save every response to a cassette directory
+//   replay  serve responses from the cassette directory without touching the network
+//
+// The mode is read from METRICS_HTTP_MODE and the cassette directory from
+// METRICS_CASSETTE_DIR (default "tests/cassettes"). Live and recorded traffic can
+// additionally go through the on-disk cache in `cache`, and always goes through the
+// rate limit tracking in `rate_limit`.
+pub mod cache;
+pub mod graphql;
+pub mod link;
+pub mod rate_limit;
+
+use cache::CachingTransport;
+use rate_limit::{RateLimitedTransport, RateLimits};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    // not saved in cassettes, they carry the access token
+    #[serde(skip)]
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+    // body the key is computed from instead, without values that change between runs
+    #[serde(skip)]
+    pub key_body: Option<String>,
+    // package the request is made for, used to report API usage
+    #[serde(skip)]
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HttpResponse {
+    pub status: u16,
+    // header names are lowercase
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+#[derive(Debug)]
+pub enum HttpError {
+    // request could not be sent or response could not be read
+    Network(String),
+    // no recorded response for the request in replay mode
+    NotRecorded(String),
+    // cassette could not be read or written
+    Io(std::io::Error),
+    // response body is not valid json
+    Json(serde_json::Error),
+    // API budget exhausted, reset is the unix time it is replenished
+    RateLimited { resource: String, reset: u64 },
+    // unsuccessful response status
+    Status { status: u16, url: String },
+    // errors reported in a GraphQL response
+    Graphql(Vec<graphql::GraphqlError>),
+}
+
+pub type Result<T> = std::result::Result<T, HttpError>;
+
+pub trait Transport: Send + Sync + std::fmt::Debug {
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse>;
+
+    // whether the transport works without network access (and credentials)
+    fn is_offline(&self) -> bool {
+        false
+    }
+}
+
+impl HttpRequest {
+    pub fn get(url: &str) -> HttpRequest {
+        HttpRequest {
+            method: "GET".to_string(),
+            url: url.to_string(),
+            headers: Vec::new(),
+            body: None,
+            key_body: None,
+            label: None,
+        }
+    }
+
+    pub fn post(url: &str, body: String) -> HttpRequest {
+        HttpRequest {
+            method: "POST".to_string(),
+            url: url.to_string(),
+            headers: Vec::new(),
+            body: Some(body),
+            key_body: None,
+            label: None,
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> HttpRequest {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn label(mut self, label: &str) -> HttpRequest {
+        self.label = Some(label.to_string());
+        self
+    }
+
+    // compute the key from `body`, e.g. the query without today's date
+    pub fn keyed_on(mut self, body: String) -> HttpRequest {
+        self.key_body = Some(body);
+        self
+    }
+
+    // stable identifier of the request, used as cassette file name
+    pub fn key(&self) -> String {
+        let body = self.key_body.as_ref().or(self.body.as_ref());
+        hash(&format!(
+            "{} {}\n{}",
+            self.method,
+            self.url,
+            body.map(String::as_str).unwrap_or("")
+        ))
+    }
+
+    pub fn header_value(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+impl HttpResponse {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        let name = name.to_lowercase();
+        self.headers
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    // the response itself if successful, an HttpError::Status otherwise
+    pub fn error_for_status(self, url: &str) -> Result<HttpResponse> {
+        if self.is_success() {
+            Ok(self)
+        } else {
+            Err(HttpError::Status {
+                status: self.status,
+                url: url.to_string(),
+            })
+        }
+    }
+
+    pub fn json(&self) -> Result<serde_json::Value> {
+        serde_json::from_str(&self.body).map_err(HttpError::Json)
+    }
+}
+
+impl std::fmt::Display for HttpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HttpError::Network(e) => write!(f, "network error: {}", e),
+            HttpError::NotRecorded(r) => write!(f, "no recorded response for {}", r),
+            HttpError::Io(e) => write!(f, "cassette error: {}", e),
+            HttpError::Json(e) => write!(f, "invalid json in response: {}", e),
+            HttpError::Graphql(errors) => write!(
+                f,
+                "graphql error: {}",
+                errors
+                    .iter()
+                    .map(|e| e.message.as_str())
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            ),
+            HttpError::Status { status, url } => write!(f, "{} returned status {}", url, status),
+            HttpError::RateLimited { resource, reset } => write!(
+                f,
+                "{} rate limit exhausted, resets in {}s",
+                resource,
+                reset.saturating_sub(unix_now())
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+impl From<reqwest::Error> for HttpError {
+    fn from(e: reqwest::Error) -> HttpError {
+        HttpError::Network(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for HttpError {
+    fn from(e: std::io::Error) -> HttpError {
+        HttpError::Io(e)
+    }
+}
+
+// FNV-1a so that file names do not change between Rust versions
+pub fn hash(text: &str) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in text.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+// seconds since the unix epoch
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// transport selected by METRICS_HTTP_MODE and METRICS_CASSETTE_DIR
+pub fn transport_from_env() -> Arc<dyn Transport> {
+    transport_with(cache_from_env(), RateLimits::global())
+}
+
+// responses are cached on disk when METRICS_CACHE_DIR is set
+pub fn cache_from_env() -> Option<(PathBuf, std::time::Duration)> {
+    let cache_dir = std::env::var("METRICS_CACHE_DIR").ok()?;
+    let ttl = std::env::var("METRICS_CACHE_TTL")
+        .ok()
+        .and_then(|t| t.parse::<u64>().ok())
+        .map(std::time::Duration::from_secs)
+        .unwrap_or(cache::DEFAULT_TTL);
+    Some((PathBuf::from(cache_dir), ttl))
+}
+
+// transport caching responses in (directory, TTL) if given, with the API budget
+// tracked in `limits`, e.g. one per token
+pub fn transport_with(
+    cache: Option<(PathBuf, std::time::Duration)>,
+    limits: Arc<RateLimits>,
+) -> Arc<dyn Transport> {
+    let dir =
+        std::env::var("METRICS_CASSETTE_DIR").unwrap_or_else(|_| "tests/cassettes".to_string());
+    let mode = std::env::var("METRICS_HTTP_MODE");
+    if mode.as_deref() == Ok("replay") {
+        return Arc::new(ReplayTransport::new(dir));
+    }
+
+    let mut network: Box<dyn Transport> = Box::new(RateLimitedTransport::from_env(
+        Box::new(ReqwestTransport::new()),
+        limits,
+    ));
+    if let Some((cache_dir, ttl)) = cache {
+        network = Box::new(CachingTransport::new(network, cache_dir, ttl));
+    }
+
+    if mode.as_deref() == Ok("record") {
+        network = Box::new(RecordingTransport::new(network, dir));
+    }
+    Arc::from(network)
+}
+
+// sends requests over the network
+#[derive(Debug)]
+pub struct ReqwestTransport {
+    client: reqwest::blocking::Client,
+}
+
+impl ReqwestTransport {
+    pub fn new() -> ReqwestTransport {
+        ReqwestTransport {
+            client: reqwest::blocking::Client::builder()
+                .user_agent("ECE461_Team19_CLI")
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl Default for ReqwestTransport {
+    fn default() -> Self {
+        ReqwestTransport::new()
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse> {
+        let method = reqwest::Method::from_bytes(request.method.as_bytes())
+            .map_err(|e| HttpError::Network(e.to_string()))?;
+        let mut builder = self.client.request(method, &request.url);
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        if let Some(body) = &request.body {
+            builder = builder.body(body.clone());
+        }
+
+        let response = builder.send()?;
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(n, v)| Some((n.as_str().to_lowercase(), v.to_str().ok()?.to_string())))
+            .collect();
+        let body = response.text()?;
+
+        Ok(HttpResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+}
+
+// a recorded request/response pair, stored as one json file per request
+#[derive(Debug, Serialize, Deserialize)]
+struct Cassette {
+    request: HttpRequest,
+    response: HttpResponse,
+}
+
+fn cassette_path(dir: &Path, request: &HttpRequest) -> PathBuf {
+    dir.join(format!("{}.json", request.key()))
+}
+
+// forwards requests to another transport and saves the responses
+#[derive(Debug)]
+pub struct RecordingTransport {
+    inner: Box<dyn Transport>,
+    dir: PathBuf,
+}
+
+impl RecordingTransport {
+    pub fn new(inner: Box<dyn Transport>, dir: impl Into<PathBuf>) -> RecordingTransport {
+        RecordingTransport {
+            inner,
+            dir: dir.into(),
+        }
+    }
+}
+
+impl Transport for RecordingTransport {
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse> {
+        let response = self.inner.send(request)?;
+
+        std::fs::create_dir_all(&self.dir)?;
+        let cassette = Cassette {
+            request: request.clone(),
+            response: response.clone(),
+        };
+        let text = serde_json::to_string_pretty(&cassette).map_err(HttpError::Json)?;
+        std::fs::write(cassette_path(&self.dir, request), text)?;
+
+        Ok(response)
+    }
+}
+
+// serves responses saved by RecordingTransport
+#[derive(Debug)]
+pub struct ReplayTransport {
+    dir: PathBuf,
+}
+
+impl ReplayTransport {
+    pub fn new(dir: impl Into<PathBuf>) -> ReplayTransport {
+        ReplayTransport { dir: dir.into() }
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse> {
+        let text = std::fs::read_to_string(cassette_path(&self.dir, request)).map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                HttpError::NotRecorded(format!("{} {}", request.method, request.url))
+            } else {
+                HttpError::Io(e)
+            }
+        })?;
+        let cassette: Cassette = serde_json::from_str(&text).map_err(HttpError::Json)?;
+        Ok(cassette.response)
+    }
+
+    fn is_offline(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    // transport answering from a fixed table of url -> response
+    #[derive(Debug, Default)]
+    pub struct StubTransport {
+        pub responses: HashMap<String, HttpResponse>,
+    }
+
+    impl StubTransport {
+        pub fn with(self, url: &str, status: u16, body: &str) -> StubTransport {
+            self.with_headers(url, status, body, &[])
+        }
+
+        pub fn with_headers(
+            mut self,
+            url: &str,
+            status: u16,
+            body: &str,
+            headers: &[(&str, &str)],
+        ) -> StubTransport {
+            let mut response = HttpResponse {
+                status,
+                headers: vec![("content-type".to_string(), "application/json".to_string())],
+                body: body.to_string(),
+            };
+            for (name, value) in headers {
+                response
+                    .headers
+                    .push((name.to_lowercase(), value.to_string()));
+            }
+            self.responses.insert(url.to_string(), response);
+            self
+        }
+    }
+
+    impl Transport for StubTransport {
+        fn send(&self, request: &HttpRequest) -> Result<HttpResponse> {
+            self.responses
+                .get(&request.url)
+                .cloned()
+                .ok_or_else(|| HttpError::NotRecorded(request.url.clone()))
+        }
+
+        fn is_offline(&self) -> bool {
+            true
+        }
+    }
+
+    // hand-written responses committed to tests/cassettes, not real repositories
+    pub fn cassettes() -> ReplayTransport {
+        ReplayTransport::new("tests/cassettes")
+    }
+
+    // fresh directory under the system temp dir
+    pub fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("metrics_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn key_ignores_headers() {
+        let a = HttpRequest::get("https://example.com/a").header("authorization", "Bearer 1");
+        let b = HttpRequest::get("https://example.com/a").header("authorization", "Bearer 2");
+        assert_eq!(a.key(), b.key());
+    }
+
+    #[test]
+    fn key_depends_on_body() {
+        let a = HttpRequest::post(
+            "https://example.com/graphql",
+            "{\"query\":\"a\"}".to_string(),
+        );
+        let b = HttpRequest::post(
+            "https://example.com/graphql",
+            "{\"query\":\"b\"}".to_string(),
+        );
+        assert_ne!(a.key(), b.key());
+        assert_ne!(
+            a.key(),
+            HttpRequest::get("https://example.com/graphql").key()
+        );
+    }
+
+    #[test]
+    fn key_of_stable_body() {
+        let request = |date: &str| {
+            HttpRequest::post("https://example.com/graphql", format!("updated:>={}", date))
+                .keyed_on("updated".to_string())
+        };
+        assert_eq!(request("2024-01-01").key(), request("2024-01-02").key());
+        assert_ne!(
+            request("2024-01-01").key(),
+            HttpRequest::post("https://example.com/graphql", "updated:>=2024-01-01".to_string())
+                .key()
+        );
+    }
+
+    #[test]
+    fn record_then_replay() {
+        let dir = temp_dir("record_then_replay");
+        let stub = StubTransport::default().with("https://example.com/a", 200, "{\"a\":1}");
+        let recorder = RecordingTransport::new(Box::new(stub), &dir);
+        let request = HttpRequest::get("https://example.com/a").header("authorization", "secret");
+        let recorded = recorder.send(&request).unwrap();
+
+        // token is not written to disk
+        let saved = std::fs::read_to_string(cassette_path(&dir, &request)).unwrap();
+        assert!(!saved.contains("secret"));
+
+        let replay = ReplayTransport::new(&dir);
+        let replayed = replay.send(&request).unwrap();
+        assert_eq!(recorded, replayed);
+        assert_eq!(1, replayed.json().unwrap()["a"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn replay_missing() {
+        let replay = ReplayTransport::new(temp_dir("replay_missing"));
+        match replay.send(&HttpRequest::get("https://example.com/missing")) {
+            Err(HttpError::NotRecorded(r)) => assert_eq!("GET https://example.com/missing", r),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn response_header_case_insensitive() {
+        let r = HttpResponse {
+            status: 200,
+            headers: vec![("link".to_string(), "<a>".to_string())],
+            body: String::new(),
+        };
+        assert_eq!(Some("<a>"), r.header("Link"));
+        assert_eq!(None, r.header("etag"));
+        assert!(r.is_success());
+        assert!(matches!(
+            HttpResponse { status: 404, ..r }.error_for_status("https://example.com/"),
+            Err(HttpError::Status { status: 404, .. })
+        ));
+    }
+}
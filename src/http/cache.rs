@@ -0,0 +1,292 @@
+// persistent response cache
+//
+// Successful responses are stored on disk keyed by request and credential, clients
+// with different tokens do not share entries. Within the TTL they are
+// served without a request. After it, GET responses (the npm registry and the REST
+// API) are revalidated with If-None-Match / If-Modified-Since, and a 304 answer
+// refreshes the stored copy; GitHub does not count 304 responses against the REST
+// rate limit. POST requests, i.e. the GraphQL queries, are cached by TTL only and
+// sent again once stale, GitHub does not answer them with validators.
+//
+// Enabled by setting METRICS_CACHE_DIR, METRICS_CACHE_TTL sets the TTL in seconds
+// (default 3600).
+use crate::http::{hash, unix_now, HttpError, HttpRequest, HttpResponse, Result, Transport};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub const DEFAULT_TTL: Duration = Duration::from_secs(3600);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    // unix time in seconds the response was fetched or last revalidated
+    stored_at: u64,
+    response: HttpResponse,
+}
+
+#[derive(Debug)]
+pub struct CachingTransport {
+    inner: Box<dyn Transport>,
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl CachingTransport {
+    pub fn new(
+        inner: Box<dyn Transport>,
+        dir: impl Into<PathBuf>,
+        ttl: Duration,
+    ) -> CachingTransport {
+        CachingTransport {
+            inner,
+            dir: dir.into(),
+            ttl,
+        }
+    }
+
+    // the token is hashed, it is not written to disk
+    fn path(&self, request: &HttpRequest) -> PathBuf {
+        match request.header_value("authorization") {
+            Some(credential) => {
+                self.dir
+                    .join(format!("{}-{}.json", request.key(), hash(credential)))
+            }
+            None => self.dir.join(format!("{}.json", request.key())),
+        }
+    }
+
+    fn load(&self, request: &HttpRequest) -> Option<CacheEntry> {
+        let text = std::fs::read_to_string(self.path(request)).ok()?;
+        serde_json::from_str(&text).ok()
+    }
+
+    fn store(&self, request: &HttpRequest, entry: &CacheEntry) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let text = serde_json::to_string(entry).map_err(HttpError::Json)?;
+        std::fs::write(self.path(request), text)?;
+        Ok(())
+    }
+}
+
+impl Transport for CachingTransport {
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse> {
+        let cached = self.load(request);
+
+        let mut conditional = request.clone();
+        if let Some(entry) = &cached {
+            // fresh copy, no request needed
+            if unix_now().saturating_sub(entry.stored_at) < self.ttl.as_secs() {
+                debug!("cache hit {} {}", request.method, request.url);
+                return Ok(entry.response.clone());
+            }
+        }
+        if let Some(entry) = cached.as_ref().filter(|_| request.method == "GET") {
+            if let Some(etag) = entry.response.header("etag") {
+                conditional = conditional.header("If-None-Match", etag);
+            }
+            if let Some(modified) = entry.response.header("last-modified") {
+                conditional = conditional.header("If-Modified-Since", modified);
+            }
+        }
+
+        let response = self.inner.send(&conditional)?;
+
+        // stale copy is still valid
+        if response.status == 304 {
+            if let Some(mut entry) = cached {
+                debug!("cache revalidated {} {}", request.method, request.url);
+                entry.stored_at = unix_now();
+                self.store(request, &entry)?;
+                return Ok(entry.response);
+            }
+        }
+
+        if response.status == 200 {
+            let entry = CacheEntry {
+                stored_at: unix_now(),
+                response,
+            };
+            self.store(request, &entry)?;
+            return Ok(entry.response);
+        }
+
+        Ok(response)
+    }
+
+    fn is_offline(&self) -> bool {
+        self.inner.is_offline()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::tests::temp_dir;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    // answers with an etag and honours If-None-Match, counting requests
+    // full responses carry the number of the request in "n"
+    #[derive(Debug, Default)]
+    struct EtagTransport {
+        requests: Arc<AtomicUsize>,
+    }
+
+    impl Transport for EtagTransport {
+        fn send(&self, request: &HttpRequest) -> Result<HttpResponse> {
+            let n = self.requests.fetch_add(1, Ordering::SeqCst) + 1;
+            let revalidated = request
+                .headers
+                .iter()
+                .any(|(n, v)| n == "If-None-Match" && v == "\"v1\"");
+            Ok(HttpResponse {
+                status: if revalidated { 304 } else { 200 },
+                headers: vec![("etag".to_string(), "\"v1\"".to_string())],
+                body: if revalidated {
+                    String::new()
+                } else {
+                    format!("{{\"a\":1,\"n\":{}}}", n)
+                },
+            })
+        }
+    }
+
+    #[test]
+    fn fresh_entry_served_from_disk() {
+        let dir = temp_dir("cache_fresh");
+        let requests = Arc::new(AtomicUsize::new(0));
+        let inner = EtagTransport {
+            requests: requests.clone(),
+        };
+        let cache = CachingTransport::new(Box::new(inner), &dir, DEFAULT_TTL);
+        let request = HttpRequest::get("https://example.com/a");
+
+        let first = cache.send(&request).unwrap();
+        let second = cache.send(&request).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(1, requests.load(Ordering::SeqCst));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn stale_entry_revalidated() {
+        let dir = temp_dir("cache_stale");
+        let requests = Arc::new(AtomicUsize::new(0));
+        let inner = EtagTransport {
+            requests: requests.clone(),
+        };
+        let cache = CachingTransport::new(Box::new(inner), &dir, Duration::ZERO);
+        let request = HttpRequest::get("https://example.com/a");
+
+        cache.send(&request).unwrap();
+        let second = cache.send(&request).unwrap();
+        // 304 answer is replaced by the stored body
+        assert_eq!(200, second.status);
+        assert_eq!(1, second.json().unwrap()["a"]);
+        assert_eq!(2, requests.load(Ordering::SeqCst));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // answers with the credential of the request, counting requests
+    #[derive(Debug, Default)]
+    struct EchoTransport {
+        requests: Arc<AtomicUsize>,
+    }
+
+    impl Transport for EchoTransport {
+        fn send(&self, request: &HttpRequest) -> Result<HttpResponse> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            Ok(HttpResponse {
+                status: 200,
+                headers: Vec::new(),
+                body: request
+                    .header_value("authorization")
+                    .unwrap_or("")
+                    .to_string(),
+            })
+        }
+    }
+
+    #[test]
+    fn entries_per_credential() {
+        let dir = temp_dir("cache_credentials");
+        let requests = Arc::new(AtomicUsize::new(0));
+        let inner = EchoTransport {
+            requests: requests.clone(),
+        };
+        let cache = CachingTransport::new(Box::new(inner), &dir, DEFAULT_TTL);
+        let request =
+            |token: &str| HttpRequest::get("https://example.com/a").header("Authorization", token);
+
+        assert_eq!("Bearer 1", cache.send(&request("Bearer 1")).unwrap().body);
+        assert_eq!("Bearer 2", cache.send(&request("Bearer 2")).unwrap().body);
+        assert_eq!("Bearer 1", cache.send(&request("Bearer 1")).unwrap().body);
+        assert_eq!(2, requests.load(Ordering::SeqCst));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn stable_key_served_on_later_days() {
+        let dir = temp_dir("cache_stable_key");
+        let requests = Arc::new(AtomicUsize::new(0));
+        let inner = EchoTransport {
+            requests: requests.clone(),
+        };
+        let cache = CachingTransport::new(Box::new(inner), &dir, DEFAULT_TTL);
+        let request = |date: &str| {
+            HttpRequest::post("https://example.com/graphql", format!("updated:>={}", date))
+                .keyed_on("updated".to_string())
+        };
+
+        cache.send(&request("2024-01-01")).unwrap();
+        cache.send(&request("2024-01-02")).unwrap();
+        assert_eq!(1, requests.load(Ordering::SeqCst));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn post_cached_by_ttl() {
+        let request = HttpRequest::post("https://example.com/graphql", "{}".to_string());
+
+        let dir = temp_dir("cache_post_fresh");
+        let requests = Arc::new(AtomicUsize::new(0));
+        let inner = EtagTransport {
+            requests: requests.clone(),
+        };
+        let cache = CachingTransport::new(Box::new(inner), &dir, DEFAULT_TTL);
+        cache.send(&request).unwrap();
+        cache.send(&request).unwrap();
+        assert_eq!(1, requests.load(Ordering::SeqCst));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // stale queries are sent again without If-None-Match, the answer is a full response
+        let dir = temp_dir("cache_post_stale");
+        let requests = Arc::new(AtomicUsize::new(0));
+        let inner = EtagTransport {
+            requests: requests.clone(),
+        };
+        let cache = CachingTransport::new(Box::new(inner), &dir, Duration::ZERO);
+        cache.send(&request).unwrap();
+        let second = cache.send(&request).unwrap();
+        assert_eq!(200, second.status);
+        assert_eq!(2, second.json().unwrap()["n"]);
+        assert_eq!(2, requests.load(Ordering::SeqCst));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn errors_not_cached() {
+        let dir = temp_dir("cache_errors");
+        let stub = crate::http::tests::StubTransport::default().with(
+            "https://example.com/missing",
+            404,
+            "{}",
+        );
+        let cache = CachingTransport::new(Box::new(stub), &dir, DEFAULT_TTL);
+        let request = HttpRequest::get("https://example.com/missing");
+
+        assert_eq!(404, cache.send(&request).unwrap().status);
+        assert!(cache.load(&request).is_none());
+    }
+}
@@ -0,0 +1,191 @@
+// GraphQL requests and responses
+//
+// Queries are sent as {"query": ..., "variables": {...}} instead of interpolating
+// values into the query text, and responses are deserialized into typed structs.
+// A non-empty `errors` array is returned as HttpError::Graphql.
+// see: https://graphql.org/learn/serving-over-http/
+use crate::http::{HttpError, HttpResponse, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize)]
+pub struct GraphqlRequest<'a> {
+    pub query: &'a str,
+    pub variables: serde_json::Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct GraphqlError {
+    pub message: String,
+    // e.g. NOT_FOUND, set by GitHub
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphqlResponse {
+    // partial data accompanying errors may not match T, so it is decoded after the check
+    data: Option<serde_json::Value>,
+    #[serde(default)]
+    errors: Vec<GraphqlError>,
+}
+
+// request body for the query
+pub fn body(query: &str, variables: serde_json::Value) -> Result<String> {
+    serde_json::to_string(&GraphqlRequest { query, variables }).map_err(HttpError::Json)
+}
+
+// data of a response, or the errors GraphQL reported
+pub fn decode<T: DeserializeOwned>(url: &str, response: HttpResponse) -> Result<T> {
+    let response = response.error_for_status(url)?;
+    let decoded: GraphqlResponse =
+        serde_json::from_str(&response.body).map_err(HttpError::Json)?;
+
+    // GitHub answers 200 when the GraphQL budget is exhausted, the reset is in the headers
+    let rate_limited = decoded
+        .errors
+        .iter()
+        .any(|e| e.kind.as_deref() == Some("RATE_LIMITED"));
+    let reset = response
+        .header("x-ratelimit-reset")
+        .and_then(|r| r.parse::<u64>().ok());
+    if let (true, Some(reset)) = (rate_limited, reset) {
+        return Err(HttpError::RateLimited {
+            resource: response
+                .header("x-ratelimit-resource")
+                .unwrap_or("graphql")
+                .to_string(),
+            reset,
+        });
+    }
+    if !decoded.errors.is_empty() {
+        return Err(HttpError::Graphql(decoded.errors));
+    }
+    match decoded.data {
+        Some(data) if !data.is_null() => serde_json::from_value(data).map_err(HttpError::Json),
+        _ => Err(HttpError::Graphql(vec![GraphqlError {
+            message: "response contains no data".to_string(),
+            kind: None,
+        }])),
+    }
+}
+
+// common shapes of GitHub GraphQL responses
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TotalCount {
+    pub total_count: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Edges<T> {
+    pub edges: Vec<Edge<T>>,
+}
+
+impl<T> Default for Edges<T> {
+    fn default() -> Self {
+        Edges { edges: Vec::new() }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Edge<T> {
+    pub node: T,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct Data {
+        repository: Repository,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Repository {
+        mentionable_users: TotalCount,
+    }
+
+    fn response(status: u16, body: &str) -> HttpResponse {
+        HttpResponse {
+            status,
+            headers: Vec::new(),
+            body: body.to_string(),
+        }
+    }
+
+    #[test]
+    fn body_has_variables() {
+        let b = body(
+            "query($owner: String!) { a }",
+            serde_json::json!({"owner": "a\"b"}),
+        )
+        .unwrap();
+        let json: serde_json::Value = serde_json::from_str(&b).unwrap();
+        assert_eq!("query($owner: String!) { a }", json["query"]);
+        assert_eq!("a\"b", json["variables"]["owner"]);
+    }
+
+    #[test]
+    fn decode_data() {
+        let data: Data = decode(
+            "https://api.github.com/graphql",
+            response(
+                200,
+                "{\"data\":{\"repository\":{\"mentionableUsers\":{\"totalCount\":7}}}}",
+            ),
+        )
+        .unwrap();
+        assert_eq!(7, data.repository.mentionable_users.total_count);
+    }
+
+    #[test]
+    fn decode_errors() {
+        let result: Result<Data> = decode(
+            "https://api.github.com/graphql",
+            response(
+                200,
+                "{\"data\":{\"repository\":null},\"errors\":[{\"type\":\"NOT_FOUND\",\"message\":\"Could not resolve to a Repository\"}]}",
+            ),
+        );
+        match result {
+            Err(HttpError::Graphql(errors)) => {
+                assert_eq!(Some("NOT_FOUND".to_string()), errors[0].kind);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn decode_rate_limited() {
+        let body =
+            "{\"errors\":[{\"type\":\"RATE_LIMITED\",\"message\":\"API rate limit exceeded\"}]}";
+        let mut limited = response(200, body);
+        limited.headers = vec![
+            ("x-ratelimit-reset".to_string(), "1700000000".to_string()),
+            ("x-ratelimit-resource".to_string(), "graphql".to_string()),
+        ];
+        let result: Result<Data> = decode("https://api.github.com/graphql", limited);
+        match result {
+            Err(HttpError::RateLimited { resource, reset }) => {
+                assert_eq!(("graphql", 1700000000), (resource.as_str(), reset));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // the reset is unknown without headers
+        let result: Result<Data> = decode("https://api.github.com/graphql", response(200, body));
+        assert!(matches!(result, Err(HttpError::Graphql(_))));
+    }
+
+    #[test]
+    fn decode_status() {
+        let result: Result<Data> = decode(
+            "https://api.github.com/graphql",
+            response(401, "{\"message\":\"Bad credentials\"}"),
+        );
+        assert!(matches!(result, Err(HttpError::Status { status: 401, .. })));
+    }
+}
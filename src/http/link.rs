@@ -0,0 +1,178 @@
+// Link header parsing (RFC 8288), used for REST API pagination
+// see: https://www.rfc-editor.org/rfc/rfc8288#section-3
+//
+//   Link: <https://api.github.com/...?page=2>; rel="next", <...?page=5>; rel="last"
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    pub uri: String,
+    // parameter names are lowercase, values unquoted
+    pub params: Vec<(String, String)>,
+}
+
+impl Link {
+    pub fn param(&self, name: &str) -> Option<&str> {
+        let name = name.to_lowercase();
+        self.params
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    // rel can hold several space separated relation types
+    pub fn has_rel(&self, rel: &str) -> bool {
+        self.param("rel")
+            .map(|r| r.split_whitespace().any(|r| r.eq_ignore_ascii_case(rel)))
+            .unwrap_or(false)
+    }
+
+    // value of a query parameter of the target uri, e.g. "page"
+    pub fn query(&self, name: &str) -> Option<String> {
+        let url = reqwest::Url::parse(&self.uri).ok()?;
+        let value = url.query_pairs().find(|(n, _)| n == name);
+        value.map(|(_, v)| v.into_owned())
+    }
+}
+
+// parse all links of a header value, malformed links are skipped
+pub fn parse(header: &str) -> Vec<Link> {
+    let mut links = Vec::new();
+    let mut rest = header;
+
+    loop {
+        // target uri in angle brackets
+        rest = rest.trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+        let Some(start) = rest.strip_prefix('<') else {
+            // skip to the next link
+            match rest.find(',') {
+                Some(i) => {
+                    rest = &rest[i + 1..];
+                    continue;
+                }
+                None => break,
+            }
+        };
+        let Some(end) = start.find('>') else {
+            break;
+        };
+        let uri = start[..end].trim().to_string();
+        rest = &start[end + 1..];
+
+        // ; separated parameters until the next link
+        let mut params = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            let Some(param) = rest.strip_prefix(';') else {
+                break;
+            };
+            let param = param.trim_start();
+            let name_end = param
+                .find(['=', ';', ','])
+                .unwrap_or(param.len());
+            let name = param[..name_end].trim().to_lowercase();
+            rest = &param[name_end..];
+
+            let mut value = String::new();
+            if let Some(v) = rest.strip_prefix('=') {
+                let v = v.trim_start();
+                if let Some(quoted) = v.strip_prefix('"') {
+                    // quoted string with backslash escapes
+                    let mut chars = quoted.char_indices();
+                    let mut consumed = quoted.len();
+                    while let Some((i, c)) = chars.next() {
+                        match c {
+                            '\\' => {
+                                if let Some((_, escaped)) = chars.next() {
+                                    value.push(escaped);
+                                }
+                            }
+                            '"' => {
+                                consumed = i + 1;
+                                break;
+                            }
+                            c => value.push(c),
+                        }
+                    }
+                    rest = &quoted[consumed..];
+                } else {
+                    let value_end = v.find([';', ',']).unwrap_or(v.len());
+                    value = v[..value_end].trim().to_string();
+                    rest = &v[value_end..];
+                }
+            }
+            if !name.is_empty() {
+                params.push((name, value));
+            }
+        }
+
+        links.push(Link { uri, params });
+    }
+
+    links
+}
+
+// first link with the given relation type
+pub fn find_rel<'a>(links: &'a [Link], rel: &str) -> Option<&'a Link> {
+    links.iter().find(|l| l.has_rel(rel))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn github_pagination() {
+        let links = parse(
+            "<https://api.github.com/repositories/1/issues?state=all&per_page=1&page=2>; rel=\"next\", \
+             <https://api.github.com/repositories/1/issues?state=all&per_page=1&page=1234>; rel=\"last\"",
+        );
+        assert_eq!(2, links.len());
+        assert_eq!(
+            Some("2".to_string()),
+            find_rel(&links, "next").unwrap().query("page")
+        );
+        assert_eq!(
+            Some("1234".to_string()),
+            find_rel(&links, "last").unwrap().query("page")
+        );
+        assert!(find_rel(&links, "prev").is_none());
+    }
+
+    #[test]
+    fn reordered_rels_and_question_mark_page() {
+        let links = parse(
+            "<https://api.github.com/x?page=9>; rel=\"last\", <https://api.github.com/x?page=3>; rel=\"next\", \
+             <https://api.github.com/x?page=1>; rel=\"first\", <https://api.github.com/x?page=1>; rel=\"prev\"",
+        );
+        assert_eq!(4, links.len());
+        assert_eq!(
+            Some("9".to_string()),
+            find_rel(&links, "last").unwrap().query("page")
+        );
+        assert_eq!(
+            Some("3".to_string()),
+            find_rel(&links, "next").unwrap().query("page")
+        );
+    }
+
+    #[test]
+    fn quoted_commas_and_multiple_rels() {
+        let links = parse(
+            "<https://example.com/a,b>; title=\"a, \\\"quoted\\\"; title\"; rel=\"next last\", <https://example.com/c>;rel=prev",
+        );
+        assert_eq!(2, links.len());
+        assert_eq!("https://example.com/a,b", links[0].uri);
+        assert_eq!(Some("a, \"quoted\"; title"), links[0].param("title"));
+        assert!(links[0].has_rel("next"));
+        assert!(links[0].has_rel("LAST"));
+        assert_eq!(Some("prev"), links[1].param("rel"));
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(parse("").is_empty());
+        assert!(parse("garbage").is_empty());
+        assert!(parse("<https://example.com/unterminated; rel=next").is_empty());
+        assert_eq!(1, parse("garbage, <https://example.com/>; rel=next").len());
+    }
+}
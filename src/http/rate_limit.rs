@@ -0,0 +1,475 @@
+// GitHub rate limit tracking
+//
+// Reads the X-RateLimit-* headers of every response to keep track of the remaining
+// budget per API resource (core, graphql, search). When a budget is exhausted, or
+// GitHub answers with a secondary rate limit (403/429 with Retry-After), requests
+// wait for the reset if it is at most METRICS_RATE_LIMIT_MAX_WAIT seconds away
+// (default 60) and fail with HttpError::RateLimited otherwise.
+// see: https://docs.github.com/en/rest/overview/resources-in-the-rest-api?apiVersion=2022-11-28#rate-limiting
+//
+// Budgets are kept per token, clients sharing a token share its budget. Requests are
+// also counted per package label, for a summary at the end of a run.
+use crate::http::{hash, unix_now, HttpError, HttpRequest, HttpResponse, Result, Transport};
+use log::{info, warn};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+pub const DEFAULT_MAX_WAIT: Duration = Duration::from_secs(60);
+
+// how often a secondary rate limit is waited out before giving up
+const MAX_RETRIES: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Budget {
+    pub limit: u64,
+    pub remaining: u64,
+    // unix time the budget is replenished
+    pub reset: u64,
+}
+
+// API cost of one package
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Usage {
+    // requests per resource
+    pub requests: BTreeMap<String, u32>,
+    // conditional requests answered with 304, free for the REST API
+    pub not_modified: u32,
+    // time spent waiting for rate limits
+    pub waited: Duration,
+}
+
+// token, host and resource a budget is tracked for
+type BudgetKey = (String, String, String);
+
+// budgets and usage shared by all clients of a run
+#[derive(Debug, Default)]
+pub struct RateLimits {
+    // keyed by (hash of the Authorization header, host, resource)
+    budgets: Mutex<HashMap<BudgetKey, Budget>>,
+    usage: Mutex<BTreeMap<String, Usage>>,
+}
+
+impl RateLimits {
+    pub fn new() -> RateLimits {
+        RateLimits::default()
+    }
+
+    // process wide instance, GitHub budgets belong to the token and not to a client
+    pub fn global() -> Arc<RateLimits> {
+        static GLOBAL: OnceLock<Arc<RateLimits>> = OnceLock::new();
+        GLOBAL.get_or_init(|| Arc::new(RateLimits::new())).clone()
+    }
+
+    // budget of the Authorization header value, None for anonymous requests
+    pub fn budget(
+        &self,
+        authorization: Option<&str>,
+        host: &str,
+        resource: &str,
+    ) -> Option<Budget> {
+        self.budgets
+            .lock()
+            .unwrap()
+            .get(&(
+                credential(authorization),
+                host.to_string(),
+                resource.to_string(),
+            ))
+            .copied()
+    }
+
+    pub fn usage(&self) -> BTreeMap<String, Usage> {
+        self.usage.lock().unwrap().clone()
+    }
+
+    // human readable usage per package and remaining budgets
+    pub fn summary(&self) -> String {
+        let mut lines = Vec::new();
+        for (label, usage) in self.usage() {
+            let requests = usage
+                .requests
+                .iter()
+                .map(|(r, n)| format!("{} {}", r, n))
+                .collect::<Vec<_>>()
+                .join(", ");
+            lines.push(format!(
+                "{}: {} ({} not modified, waited {}s)",
+                label,
+                requests,
+                usage.not_modified,
+                usage.waited.as_secs()
+            ));
+        }
+
+        let mut budgets = self
+            .budgets
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|((credential, host, resource), b)| {
+                // a prefix of the hash tells tokens apart without showing them
+                let token = match credential.get(..8) {
+                    Some(prefix) => format!(" (token {})", prefix),
+                    None => String::new(),
+                };
+                format!(
+                    "{} {}{}: {}/{} remaining, resets in {}s",
+                    host,
+                    resource,
+                    token,
+                    b.remaining,
+                    b.limit,
+                    b.reset.saturating_sub(unix_now())
+                )
+            })
+            .collect::<Vec<_>>();
+        budgets.sort();
+        lines.extend(budgets);
+        lines.join("\n")
+    }
+
+    fn record(&self, label: &str, update: impl FnOnce(&mut Usage)) {
+        let mut usage = self.usage.lock().unwrap();
+        update(usage.entry(label.to_string()).or_default());
+    }
+}
+
+// host of a url, budgets are tracked per host
+fn host(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+// hash of the Authorization header value, empty for anonymous requests
+fn credential(authorization: Option<&str>) -> String {
+    authorization.map(hash).unwrap_or_default()
+}
+
+// resource a request is counted against, before the response tells
+fn expected_resource(request: &HttpRequest) -> &'static str {
+    if request.url.ends_with("/graphql") {
+        "graphql"
+    } else if request.url.contains("/search/") {
+        "search"
+    } else {
+        "core"
+    }
+}
+
+fn header_u64(response: &HttpResponse, name: &str) -> Option<u64> {
+    response.header(name)?.trim().parse().ok()
+}
+
+#[derive(Debug)]
+pub struct RateLimitedTransport {
+    inner: Box<dyn Transport>,
+    limits: Arc<RateLimits>,
+    max_wait: Duration,
+    sleep: fn(Duration),
+}
+
+impl RateLimitedTransport {
+    pub fn new(
+        inner: Box<dyn Transport>,
+        limits: Arc<RateLimits>,
+        max_wait: Duration,
+    ) -> RateLimitedTransport {
+        RateLimitedTransport {
+            inner,
+            limits,
+            max_wait,
+            sleep: std::thread::sleep,
+        }
+    }
+
+    // max wait read from METRICS_RATE_LIMIT_MAX_WAIT
+    pub fn from_env(inner: Box<dyn Transport>, limits: Arc<RateLimits>) -> RateLimitedTransport {
+        let max_wait = std::env::var("METRICS_RATE_LIMIT_MAX_WAIT")
+            .ok()
+            .and_then(|t| t.parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_MAX_WAIT);
+        RateLimitedTransport::new(inner, limits, max_wait)
+    }
+
+    // sleep for wait if allowed, otherwise fail
+    fn wait(&self, label: &str, resource: &str, wait: Duration) -> Result<()> {
+        if wait > self.max_wait {
+            return Err(HttpError::RateLimited {
+                resource: resource.to_string(),
+                reset: unix_now() + wait.as_secs(),
+            });
+        }
+        warn!(
+            "{} rate limit reached, waiting {}s",
+            resource,
+            wait.as_secs()
+        );
+        (self.sleep)(wait);
+        self.limits.record(label, |u| u.waited += wait);
+        Ok(())
+    }
+
+    // update the budget from the response headers, returns the resource
+    fn update(&self, host: &str, request: &HttpRequest, response: &HttpResponse) -> String {
+        let resource = response
+            .header("x-ratelimit-resource")
+            .unwrap_or_else(|| expected_resource(request))
+            .to_string();
+        if let (Some(limit), Some(remaining), Some(reset)) = (
+            header_u64(response, "x-ratelimit-limit"),
+            header_u64(response, "x-ratelimit-remaining"),
+            header_u64(response, "x-ratelimit-reset"),
+        ) {
+            let authorization = request.header_value("authorization");
+            self.limits.budgets.lock().unwrap().insert(
+                (
+                    credential(authorization),
+                    host.to_string(),
+                    resource.clone(),
+                ),
+                Budget {
+                    limit,
+                    remaining,
+                    reset,
+                },
+            );
+        }
+        resource
+    }
+}
+
+impl Transport for RateLimitedTransport {
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse> {
+        let host = host(&request.url);
+        let label = request.label.clone().unwrap_or_else(|| host.clone());
+
+        // primary rate limit known to be exhausted
+        let expected = expected_resource(request);
+        let authorization = request.header_value("authorization");
+        if let Some(budget) = self.limits.budget(authorization, &host, expected) {
+            if budget.remaining == 0 && budget.reset > unix_now() {
+                self.wait(
+                    &label,
+                    expected,
+                    Duration::from_secs(budget.reset.saturating_sub(unix_now())),
+                )?;
+            }
+        }
+
+        let mut attempt = 0;
+        loop {
+            let response = self.inner.send(request)?;
+            let resource = self.update(&host, request, &response);
+
+            let limited = response.status == 429
+                || (response.status == 403
+                    && (response.header("retry-after").is_some()
+                        || header_u64(&response, "x-ratelimit-remaining") == Some(0)));
+            if !limited {
+                self.limits.record(&label, |u| {
+                    if response.status == 304 {
+                        u.not_modified += 1;
+                    } else {
+                        *u.requests.entry(resource.clone()).or_insert(0) += 1;
+                    }
+                });
+                return Ok(response);
+            }
+
+            // secondary rate limits tell how long to wait, primary ones when they reset
+            attempt += 1;
+            self.limits.record(&label, |u| {
+                *u.requests.entry(resource.clone()).or_insert(0) += 1
+            });
+            let wait = match header_u64(&response, "retry-after") {
+                Some(seconds) => Duration::from_secs(seconds),
+                None => Duration::from_secs(
+                    header_u64(&response, "x-ratelimit-reset")
+                        .unwrap_or(0)
+                        .saturating_sub(unix_now()),
+                ),
+            };
+            if attempt > MAX_RETRIES {
+                return Err(HttpError::RateLimited {
+                    resource,
+                    reset: unix_now() + wait.as_secs(),
+                });
+            }
+            info!("rate limited on {}, attempt {}", request.url, attempt);
+            self.wait(&label, &resource, wait)?;
+        }
+    }
+
+    fn is_offline(&self) -> bool {
+        self.inner.is_offline()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // answers with the given responses in order, then repeats the last one
+    #[derive(Debug)]
+    struct SequenceTransport {
+        responses: Vec<HttpResponse>,
+        sent: AtomicUsize,
+    }
+
+    impl SequenceTransport {
+        fn new(responses: Vec<HttpResponse>) -> SequenceTransport {
+            SequenceTransport {
+                responses,
+                sent: AtomicUsize::new(0),
+            }
+        }
+    }
+
+    impl Transport for SequenceTransport {
+        fn send(&self, _request: &HttpRequest) -> Result<HttpResponse> {
+            let i = self.sent.fetch_add(1, Ordering::SeqCst);
+            Ok(self.responses[i.min(self.responses.len() - 1)].clone())
+        }
+    }
+
+    fn response(status: u16, headers: &[(&str, &str)]) -> HttpResponse {
+        HttpResponse {
+            status,
+            headers: headers
+                .iter()
+                .map(|(n, v)| (n.to_string(), v.to_string()))
+                .collect(),
+            body: "{}".to_string(),
+        }
+    }
+
+    fn transport(responses: Vec<HttpResponse>, limits: Arc<RateLimits>) -> RateLimitedTransport {
+        let mut t = RateLimitedTransport::new(
+            Box::new(SequenceTransport::new(responses)),
+            limits,
+            DEFAULT_MAX_WAIT,
+        );
+        t.sleep = |_| {};
+        t
+    }
+
+    #[test]
+    fn tracks_budget_and_usage() {
+        let limits = Arc::new(RateLimits::new());
+        let reset = (unix_now() + 100).to_string();
+        let t = transport(
+            vec![response(
+                200,
+                &[
+                    ("x-ratelimit-limit", "5000"),
+                    ("x-ratelimit-remaining", "4999"),
+                    ("x-ratelimit-reset", &reset),
+                    ("x-ratelimit-resource", "core"),
+                ],
+            )],
+            limits.clone(),
+        );
+        let request = HttpRequest::get("https://api.github.com/repos/a/b/license").label("a/b");
+        t.send(&request).unwrap();
+        t.send(&request).unwrap();
+
+        let budget = limits.budget(None, "api.github.com", "core").unwrap();
+        assert_eq!(4999, budget.remaining);
+        assert_eq!(Some(&2), limits.usage()["a/b"].requests.get("core"));
+        assert!(limits.summary().contains("a/b: core 2"));
+    }
+
+    #[test]
+    fn budgets_per_token() {
+        let limits = Arc::new(RateLimits::new());
+        let reset = (unix_now() + 3600).to_string();
+        let exhausted = response(
+            200,
+            &[
+                ("x-ratelimit-limit", "5000"),
+                ("x-ratelimit-remaining", "0"),
+                ("x-ratelimit-reset", &reset),
+                ("x-ratelimit-resource", "core"),
+            ],
+        );
+        let request = |token: &str| {
+            HttpRequest::get("https://api.github.com/repos/a/b").header("Authorization", token)
+        };
+        transport(vec![exhausted], limits.clone())
+            .send(&request("Bearer 1"))
+            .unwrap();
+
+        // another client of the same token knows the budget is exhausted
+        let same_token = transport(vec![response(200, &[])], limits.clone());
+        assert!(matches!(
+            same_token.send(&request("Bearer 1")),
+            Err(HttpError::RateLimited { .. })
+        ));
+        // other tokens have budgets of their own
+        let other_token = transport(vec![response(200, &[])], limits.clone());
+        assert_eq!(200, other_token.send(&request("Bearer 2")).unwrap().status);
+        assert!(limits
+            .budget(Some("Bearer 2"), "api.github.com", "core")
+            .is_none());
+        assert!(!limits.summary().contains("Bearer"));
+    }
+
+    #[test]
+    fn secondary_limit_retried() {
+        let limits = Arc::new(RateLimits::new());
+        let t = transport(
+            vec![response(403, &[("retry-after", "5")]), response(200, &[])],
+            limits.clone(),
+        );
+        let request = HttpRequest::post("https://api.github.com/graphql", "{}".to_string());
+        assert_eq!(200, t.send(&request).unwrap().status);
+
+        let usage = &limits.usage()["api.github.com"];
+        assert_eq!(Duration::from_secs(5), usage.waited);
+        assert_eq!(Some(&2), usage.requests.get("graphql"));
+    }
+
+    #[test]
+    fn long_wait_fails() {
+        let limits = Arc::new(RateLimits::new());
+        let reset = (unix_now() + 3600).to_string();
+        let t = transport(
+            vec![response(
+                403,
+                &[
+                    ("x-ratelimit-limit", "5000"),
+                    ("x-ratelimit-remaining", "0"),
+                    ("x-ratelimit-reset", &reset),
+                ],
+            )],
+            limits.clone(),
+        );
+        let request = HttpRequest::get("https://api.github.com/repos/a/b/issues");
+        match t.send(&request) {
+            Err(HttpError::RateLimited { resource, .. }) => assert_eq!("core", resource),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // exhausted budget is known before the next request
+        assert!(matches!(
+            t.send(&request),
+            Err(HttpError::RateLimited { .. })
+        ));
+    }
+
+    #[test]
+    fn not_modified_is_free() {
+        let limits = Arc::new(RateLimits::new());
+        let t = transport(vec![response(304, &[])], limits.clone());
+        t.send(&HttpRequest::get("https://api.github.com/repos/a/b").label("a/b"))
+            .unwrap();
+        let usage = &limits.usage()["a/b"];
+        assert_eq!(1, usage.not_modified);
+        assert!(usage.requests.is_empty());
+    }
+}
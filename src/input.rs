@@ -0,0 +1,135 @@
+// urls to score, from files, stdin or the command line
+//
+// Every input is a url, a file with one url per line or `-` for stdin. Blank lines
+// and lines starting with # are skipped. Urls are normalized so that spellings of the
+// same module are scored once, in the order they first appear.
+use std::collections::HashSet;
+use std::io::Read;
+
+pub fn read_inputs(inputs: &[String], mut stdin: impl Read) -> Result<Vec<String>, String> {
+    let mut urls = Vec::new();
+    for input in inputs {
+        if input == "-" {
+            let mut text = String::new();
+            stdin
+                .read_to_string(&mut text)
+                .map_err(|e| format!("failed to read stdin: {}", e))?;
+            urls.extend(parse_urls(&text));
+        } else if input.contains("://") {
+            urls.push(normalize(input));
+        } else {
+            let text = std::fs::read_to_string(input)
+                .map_err(|e| format!("failed to read {}: {}", input, e))?;
+            urls.extend(parse_urls(&text));
+        }
+    }
+    Ok(dedup(urls))
+}
+
+// normalized urls of a url file, without duplicates
+pub fn parse_urls(text: &str) -> Vec<String> {
+    dedup(
+        text.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(normalize)
+            .collect(),
+    )
+}
+
+// https://GitHub.com/owner/repo.git/?tab=readme -> https://github.com/owner/repo
+// anything that is not a url is kept and reported when scored
+pub fn normalize(url: &str) -> String {
+    let url = url.trim();
+    let Ok(mut u) = reqwest::Url::parse(url) else {
+        return url.to_string();
+    };
+    u.set_query(None);
+    u.set_fragment(None);
+    let mut path = u.path().trim_end_matches('/');
+    // clone urls of repositories, npm package names may end in .git
+    if u.host_str() != Some("www.npmjs.com") {
+        path = path.strip_suffix(".git").unwrap_or(path);
+    }
+    let path = path.to_string();
+    u.set_path(&path);
+    u.to_string().trim_end_matches('/').to_string()
+}
+
+// keep the first of every url
+fn dedup(urls: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    urls.into_iter()
+        .filter(|url| seen.insert(url.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalized() {
+        assert_eq!(
+            "https://github.com/owner/repo",
+            normalize(" https://GitHub.com/owner/repo.git/?tab=readme#license ")
+        );
+        assert_eq!(
+            "https://www.npmjs.com/package/left-pad",
+            normalize("https://www.npmjs.com/package/left-pad/")
+        );
+        assert_eq!(
+            "https://www.npmjs.com/package/node.git",
+            normalize("https://www.npmjs.com/package/node.git")
+        );
+        assert_eq!("not a url", normalize("not a url"));
+    }
+
+    #[test]
+    fn comments_and_duplicates_skipped() {
+        let text = "# modules to score\n\
+                    https://github.com/owner/repo\n\
+                    \n\
+                    \t\n\
+                    https://github.com/owner/repo/\n\
+                    https://www.npmjs.com/package/left-pad\n\
+                    https://github.com/owner/repo.git\n";
+        assert_eq!(
+            vec![
+                "https://github.com/owner/repo",
+                "https://www.npmjs.com/package/left-pad"
+            ],
+            parse_urls(text)
+        );
+    }
+
+    #[test]
+    fn files_stdin_and_arguments() {
+        let path = std::env::temp_dir().join(format!("urls-{}.txt", std::process::id()));
+        std::fs::write(
+            &path,
+            "https://github.com/owner/a\nhttps://github.com/owner/b\n",
+        )
+        .unwrap();
+        let inputs = vec![
+            "https://github.com/owner/b/".to_string(),
+            path.to_string_lossy().to_string(),
+            "-".to_string(),
+        ];
+        let stdin = "# from stdin\nhttps://github.com/owner/c\nhttps://github.com/owner/a\n";
+        let urls = read_inputs(&inputs, stdin.as_bytes());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            vec![
+                "https://github.com/owner/b",
+                "https://github.com/owner/a",
+                "https://github.com/owner/c"
+            ],
+            urls.unwrap()
+        );
+
+        assert!(read_inputs(&["missing.txt".to_string()], std::io::empty())
+            .unwrap_err()
+            .starts_with("failed to read missing.txt"));
+    }
+}
@@ -1,18 +1,15 @@
 mod file_parser;
-mod metrics;
+mod format;
+mod input;
 
-use std::fs::File;
-use std::fs;
 use clap::{Parser, Subcommand};
-use log::{debug, info, LevelFilter};
-use crate::metrics::github::Github;
-use crate::metrics::npm::Npm;
-use crate::metrics::Metrics;
-use std::io::Write;
-use std::{
-    collections::HashMap,
-    io::{BufRead, BufReader},
-};
+use log::{debug, error, info, LevelFilter};
+use crate::format::Format;
+use metricslib::http::{self, rate_limit::RateLimits};
+use metricslib::metrics::bundle::Bundle;
+use metricslib::metrics::github::GithubConfig;
+use metricslib::metrics::registry::Registry;
+use metricslib::{pool, MetricError, ScoreReport, ScoringConfig};
 
 // command line argumand parser
 #[derive(Parser)]
@@ -23,7 +20,69 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Print modules in order of trustworthiness
-    Url { url_file: String },
+    Url {
+        /// URLs, files with one URL per line, or - for stdin
+        #[arg(required = true)]
+        urls: Vec<String>,
+
+        /// Number of packages scored concurrently [default: METRICS_WORKERS or 4]
+        #[arg(short, long)]
+        workers: Option<usize>,
+
+        /// Add the inputs and reasoning behind every score to the output
+        #[arg(long)]
+        explain: bool,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = Format::Ndjson)]
+        format: Format,
+
+        /// TOML or JSON file with scoring profiles [default: METRICS_CONFIG]
+        #[arg(long)]
+        config: Option<String>,
+
+        /// Scoring profile used for the net score [default: default profile of the config]
+        #[arg(long)]
+        profile: Option<String>,
+    },
+
+    /// Save the facts of the modules to a bundle, for scoring without network access
+    Collect {
+        /// URLs, files with one URL per line, or - for stdin
+        #[arg(required = true)]
+        urls: Vec<String>,
+
+        /// Bundle file to write
+        #[arg(short, long)]
+        output: String,
+
+        /// Number of packages collected concurrently [default: METRICS_WORKERS or 4]
+        #[arg(short, long)]
+        workers: Option<usize>,
+    },
+
+    /// Print modules in order of trustworthiness, scored from a bundle of facts
+    Score {
+        /// Bundle written by the collect command
+        #[arg(long)]
+        from_bundle: String,
+
+        /// Add the inputs and reasoning behind every score to the output
+        #[arg(long)]
+        explain: bool,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = Format::Ndjson)]
+        format: Format,
+
+        /// TOML or JSON file with scoring profiles [default: METRICS_CONFIG]
+        #[arg(long)]
+        config: Option<String>,
+
+        /// Scoring profile used for the net score [default: default profile of the config]
+        #[arg(long)]
+        profile: Option<String>,
+    },
 
     /// Parse results of tests
     Report {
@@ -69,13 +128,38 @@ fn main() -> Result<(), String> {
     info!("print info");
     debug!("print debug");
 
-    let url = "https://github.com/cloudinary/cloudinary_npm";
-
     // parse command line arguments
     let cli = Cli::parse();
     match &cli.command {
-        //Commands::Url { url_file: f } => calcscore(f)?, //println!("url: {:?}", f),
-        Commands::Url { url_file: _f } => calcscore_url(&url.to_string())?, //println!("url: {:?}", f),
+        Commands::Url {
+            urls,
+            workers,
+            explain,
+            format,
+            config,
+            profile,
+        } => {
+            let mut scoring = scoring_config(config, profile)?;
+            if let Some(workers) = workers {
+                scoring.workers = *workers;
+            }
+            calcscore(urls, *explain, *format, &scoring)?
+        }
+        Commands::Collect {
+            urls,
+            output,
+            workers,
+        } => collect(urls, output, workers.unwrap_or_else(pool::workers_from_env))?,
+        Commands::Score {
+            from_bundle,
+            explain,
+            format,
+            config,
+            profile,
+        } => {
+            let scoring = scoring_config(config, profile)?;
+            score_bundle(from_bundle, *explain, *format, &scoring)?
+        }
         Commands::Report {
             test_result: t,
             line_analysis: l,
@@ -95,308 +179,126 @@ fn main() -> Result<(), String> {
     Ok(())
 }
 
-#[allow(dead_code)]
-fn calcscore(f: &String) -> Result<(), String> {
-    let mut net_scores = Vec::new();
+fn calcscore(
+    urls: &[String],
+    explain: bool,
+    format: Format,
+    config: &ScoringConfig,
+) -> Result<(), String> {
+    let lines = input::read_inputs(urls, std::io::stdin())?;
 
-    let file = std::fs::File::open(f).map_err(|e| format!("{}", e))?;
-    let reader = BufReader::new(file);
+    // score packages concurrently, results keep the order of the input
+    let results = metricslib::score_many(&lines, config);
+    write_scores(results, explain, format, &config.registry)?;
+    Ok(())
+}
 
-    for line in reader.lines() {
-        let line = line.unwrap();
-        if line.is_empty() {
-            continue;
-        }
-        info!("exploring {}", line);
-
-        // if type is github or npm
-        if let Some(domain) = reqwest::Url::parse(&line)
-            .map_err(|_| format!("{} is not a url", line))?
-            .domain()
-        {
-            let project: Box<dyn Metrics>;
-            // if github
-            if domain == "github.com" {
-                project = Box::new(
-                    Github::with_url(&line)
-                        .ok_or(format!("Error while processing url: {}", &line))?,
-                );
-            } else if domain == "www.npmjs.com" {
-                project = Box::new(
-                    Npm::with_url(&line).ok_or(format!("Error while processing url: {}", &line))?,
-                );
-            } else {
-                continue;
+// weights of the profile, curves and metrics, from the config file if given
+fn scoring_config(
+    config: &Option<String>,
+    profile: &Option<String>,
+) -> Result<ScoringConfig, String> {
+    ScoringConfig::load(config.as_deref(), profile.as_deref()).map_err(|e| e.to_string())
+}
+
+// report failed packages, print the others by net score
+fn write_scores(
+    results: Vec<(String, Result<ScoreReport, MetricError>)>,
+    explain: bool,
+    format: Format,
+    registry: &Registry,
+) -> Result<(), String> {
+    let mut net_scores = Vec::new();
+    for (line, result) in results {
+        match result {
+            Ok(net_score) => net_scores.push(net_score),
+            // report the package and keep the scores of the others
+            Err(e) => {
+                error!("failed to score {}: {}", line, e);
+                eprintln!("failed to score {}: {}", line, e);
             }
-            // calculate score
-            info!("calculating score");
-            let mut net_score = HashMap::new();
-            let ramp_up: f64 = project.ramp_up_time();
-            let correctness: f64 = project.correctness();
-            let bus_factor: f64 = project.bus_factor();
-            let responsiveness: f64 = project.responsiveness();
-            let compatibility: f64 = project.compatibility();
-            let reviewed_code: f64 = project.reviewed_code();
-            let score: f64 = ramp_up * 0.05
-                + correctness * 0.1
-                + bus_factor * 0.1
-                + responsiveness * 0.25
-                + compatibility * 0.4
-                + reviewed_code * 0.2;
-            net_score.insert("URL", line);
-            net_score.insert("NET_SCORE", score.to_string());
-            net_score.insert("RAMP_UP_SCORE", ramp_up.to_string());
-            net_score.insert("CORRECTNESS_SCORE", correctness.to_string());
-            net_score.insert("BUS_FACTOR_SCORE", bus_factor.to_string());
-            net_score.insert("RESPONSIVE_MAINTAINER_SCORE", responsiveness.to_string());
-            net_score.insert("REVIEWED_CODE_SCORE", reviewed_code.to_string());
-            net_score.insert("LICENSE_SCORE", compatibility.to_string());
-            net_scores.push(net_score);
-        } else {
-            continue;
         }
     }
     // sort by net scores
     info!("sorting by net scores");
-    net_scores.sort_by(|a, b| {
-        b["NET_SCORE"]
-            .parse::<f64>()
-            .unwrap()
-            .partial_cmp(&a["NET_SCORE"].parse::<f64>().unwrap())
-            .unwrap()
-    });
+    net_scores.sort_by(|a, b| b.net_score.total_cmp(&a.net_score));
+
+    // API cost per package
+    let usage = RateLimits::global().summary();
+    if !usage.is_empty() {
+        info!("API usage:\n{}", usage);
+        eprintln!("API usage:\n{}", usage);
+    }
 
     // stdout the output
     let stdout = std::io::stdout();
     let mut handle = stdout.lock();
 
     info!("generating output");
-    for dict in net_scores {
-        handle
-            .write_fmt(format_args!("{{\"URL\":{:?}, ", dict.get("URL").unwrap()))
-            .unwrap();
-        handle
-            .write_fmt(format_args!(
-                "\"NET_SCORE\":{:.2}, ",
-                dict.get("NET_SCORE").unwrap().parse::<f64>().unwrap()
-            ))
-            .unwrap();
-        handle
-            .write_fmt(format_args!(
-                "\"RAMP_UP_SCORE\":{:.2}, ",
-                dict.get("RAMP_UP_SCORE").unwrap().parse::<f64>().unwrap()
-            ))
-            .unwrap();
-        handle
-            .write_fmt(format_args!(
-                "\"CORRECTNESS_SCORE\":{:.2}, ",
-                dict.get("CORRECTNESS_SCORE")
-                    .unwrap()
-                    .parse::<f64>()
-                    .unwrap()
-            ))
-            .unwrap();
-        handle
-            .write_fmt(format_args!(
-                "\"BUS_FACTOR_SCORE\":{:.2}, ",
-                dict.get("BUS_FACTOR_SCORE")
-                    .unwrap()
-                    .parse::<f64>()
-                    .unwrap()
-            ))
-            .unwrap();
-        handle
-            .write_fmt(format_args!(
-                "\"REVIEWED_CODE\":{:.2}, ",
-                dict.get("REVIEWED_CODE_SCORE")
-                    .unwrap()
-                    .parse::<f64>()
-                    .unwrap()
-            ))
-            .unwrap();
-        handle
-            .write_fmt(format_args!(
-                "\"RESPONSIVE_MAINTAINER_SCORE\":{:.2}, ",
-                dict.get("RESPONSIVE_MAINTAINER_SCORE")
-                    .unwrap()
-                    .parse::<f64>()
-                    .unwrap()
-            ))
-            .unwrap();
-        handle
-            .write_fmt(format_args!(
-                "\"LICENSE_SCORE\":{}}}\n",
-                dict.get("LICENSE_SCORE").unwrap().parse::<f64>().unwrap()
-            ))
-            .unwrap();
-    }
-    Ok(())
+    let reports: Vec<ScoreReport> = net_scores
+        .into_iter()
+        .map(|mut report| {
+            if !explain && !format.explains() {
+                report.explanation = None;
+            }
+            report.rounded()
+        })
+        .collect();
+    format::write(&mut handle, format, &reports, registry).map_err(|e| e.to_string())
 }
 
-fn calcscore_url(url: &String) -> Result<(), String> {
-    println!("CALCSCORE_URL IS BEING USED");
-    let mut net_scores = Vec::new();
-
-    let file_path = "./src/url.txt";
-
-    if let Err(e) = fs::remove_file(file_path) {
-        if e.kind() != std::io::ErrorKind::NotFound {
-            // If the error is not "file not found", return an error
-            return Err(format!("Failed to remove file {}: {}", file_path, e));
-        }
-    }
-
-    let mut f = File::create(file_path).expect("Unable to create file");
-    f.write_all(url.as_bytes()).expect("Unable to write data to file");
-    let file = std::fs::File::open(file_path).map_err(|e| format!("{}", e))?;
-    let reader = BufReader::new(file);
+// collect the facts of every url into a bundle at `output`
+fn collect(urls: &[String], output: &String, workers: usize) -> Result<(), String> {
+    let github_config = GithubConfig::from_env();
+    let transport = http::transport_from_env();
+    let lines = input::read_inputs(urls, std::io::stdin())?;
 
-    for line in reader.lines() {
-        let line = line.unwrap();
-        if line.is_empty() {
-            continue;
-        }
-        info!("exploring {}", line);
-
-        // if type is github or npm
-        if let Some(domain) = reqwest::Url::parse(&line)
-            .map_err(|_| format!("{} is not a url", line))?
-            .domain()
-        {
-            let project: Box<dyn Metrics>;
-            // if github
-            if domain == "github.com" {
-                project = Box::new(
-                    Github::with_url(&line)
-                        .ok_or(format!("Error while processing url: {}", &line))?,
-                );
-            } else if domain == "www.npmjs.com" {
-                project = Box::new(
-                    Npm::with_url(&line).ok_or(format!("Error while processing url: {}", &line))?,
-                );
-            } else {
-                continue;
+    // collect packages concurrently, the bundle keeps the order of the input
+    let results = pool::parallel_map(lines, workers, |line| {
+        let result =
+            metricslib::collector(&line, &github_config, &transport).and_then(|c| c.collect());
+        (line, result)
+    });
+    let mut bundle = Bundle::default();
+    for (line, result) in results {
+        match result {
+            Ok(facts) => bundle.push(&line, facts),
+            // report the package and keep the facts of the others
+            Err(e) => {
+                error!("failed to collect {}: {}", line, e);
+                eprintln!("failed to collect {}: {}", line, e);
             }
-            // calculate score
-            info!("calculating score");
-            let mut net_score = HashMap::new();
-            let ramp_up: f64 = project.ramp_up_time();
-            let correctness: f64 = project.correctness();
-            let bus_factor: f64 = project.bus_factor();
-            let responsiveness: f64 = project.responsiveness();
-            let compatibility: f64 = project.compatibility();
-            let reviewed_code: f64 = project.reviewed_code();
-            let pinning_practice = project.pinning_practice();
-            let score: f64 = ramp_up * 0.05
-                + correctness * 0.1
-                + bus_factor * 0.1
-                + responsiveness * 0.25
-                + compatibility * 0.4
-                + reviewed_code * 0.2
-                + pinning_practice * 0.1;
-            net_score.insert("URL", line);
-            net_score.insert("NET_SCORE", score.to_string());
-            net_score.insert("RAMP_UP_SCORE", ramp_up.to_string());
-            net_score.insert("CORRECTNESS_SCORE", correctness.to_string());
-            net_score.insert("BUS_FACTOR_SCORE", bus_factor.to_string());
-            net_score.insert("RESPONSIVE_MAINTAINER_SCORE", responsiveness.to_string());
-            net_score.insert("REVIEWED_CODE_SCORE", reviewed_code.to_string());
-            net_score.insert("LICENSE_SCORE", compatibility.to_string());
-            net_score.insert("PINNING_PRACTICE_SCORE", pinning_practice.to_string());
-            net_scores.push(net_score);
-        } else {
-            continue;
         }
     }
-    // sort by net scores
-    info!("sorting by net scores");
-    net_scores.sort_by(|a, b| {
-        b["NET_SCORE"]
-            .parse::<f64>()
-            .unwrap()
-            .partial_cmp(&a["NET_SCORE"].parse::<f64>().unwrap())
-            .unwrap()
-    });
-
-    // stdout the output
-    let stdout = std::io::stdout();
-    let mut handle = stdout.lock();
 
-    info!("generating output");
-    for dict in net_scores {
-        handle
-            .write_fmt(format_args!("{{\"URL\":{:?}, ", dict.get("URL").unwrap()))
-            .unwrap();
-        handle
-            .write_fmt(format_args!(
-                "\"NET_SCORE\":{:.2}, ",
-                dict.get("NET_SCORE").unwrap().parse::<f64>().unwrap()
-            ))
-            .unwrap();
-        handle
-            .write_fmt(format_args!(
-                "\"RAMP_UP_SCORE\":{:.2}, ",
-                dict.get("RAMP_UP_SCORE").unwrap().parse::<f64>().unwrap()
-            ))
-            .unwrap();
-        handle
-            .write_fmt(format_args!(
-                "\"CORRECTNESS_SCORE\":{:.2}, ",
-                dict.get("CORRECTNESS_SCORE")
-                    .unwrap()
-                    .parse::<f64>()
-                    .unwrap()
-            ))
-            .unwrap();
-        handle
-            .write_fmt(format_args!(
-                "\"BUS_FACTOR_SCORE\":{:.2}, ",
-                dict.get("BUS_FACTOR_SCORE")
-                    .unwrap()
-                    .parse::<f64>()
-                    .unwrap()
-            ))
-            .unwrap();
-        handle
-            .write_fmt(format_args!(
-                "\"REVIEWED_CODE\":{:.2}, ",
-                dict.get("REVIEWED_CODE_SCORE")
-                    .unwrap()
-                    .parse::<f64>()
-                    .unwrap()
-            ))
-            .unwrap();
-        handle
-            .write_fmt(format_args!(
-                "\"RESPONSIVE_MAINTAINER_SCORE\":{:.2}, ",
-                dict.get("RESPONSIVE_MAINTAINER_SCORE")
-                    .unwrap()
-                    .parse::<f64>()
-                    .unwrap()
-            ))
-            .unwrap();
-        handle
-            .write_fmt(format_args!(
-                "\"PINNING_PRACTICE_SCORE\":{:.2}, ",
-                dict.get("PINNING_PRACTICE_SCORE")
-                    .unwrap()
-                    .parse::<f64>()
-                    .unwrap()
-            ))
-            .unwrap();
-        handle
-            .write_fmt(format_args!(
-                "\"LICENSE_SCORE\":{}}}\n",
-                dict.get("LICENSE_SCORE").unwrap().parse::<f64>().unwrap()
-            ))
-            .unwrap();
+    let usage = RateLimits::global().summary();
+    if !usage.is_empty() {
+        info!("API usage:\n{}", usage);
+        eprintln!("API usage:\n{}", usage);
     }
 
-    /*let owner = get_name(url);
-    println!("Owner: {}", owner);
-    let version = get_version(url);
-    println!("Version: {}", version);*/
-
+    info!("writing {} packages to {}", bundle.packages.len(), output);
+    bundle.save(output).map_err(|e| e.to_string())
+}
 
+// score the facts of a bundle, no network access
+fn score_bundle(
+    path: &String,
+    explain: bool,
+    format: Format,
+    config: &ScoringConfig,
+) -> Result<(), String> {
+    let bundle = Bundle::load(path).map_err(|e| e.to_string())?;
+    info!("scoring bundle created at {}", bundle.created_at);
+    let results = bundle
+        .packages
+        .into_iter()
+        .map(|p| {
+            debug!("{} collected at {}", p.url, p.collected_at);
+            let result = metricslib::score_facts(&p.url, p.facts, config);
+            (p.url, result)
+        })
+        .collect();
+    write_scores(results, explain, format, &config.registry)?;
     Ok(())
-}
\ No newline at end of file
+}
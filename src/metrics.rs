@@ -1,43 +1,221 @@
+pub mod bundle;
+pub mod curve;
+pub mod facts;
 pub mod github;
+pub mod identity;
 pub mod npm;
+pub mod profile;
+pub mod registry;
+pub mod report;
+pub mod scoring;
+pub mod semver;
 
-use statrs::distribution::{Continuous, Normal};
+use crate::http::HttpError;
+use crate::metrics::curve::Curve;
+use crate::metrics::facts::RepositoryFacts;
+use crate::metrics::profile::Weights;
+use crate::metrics::registry::Registry;
+use std::collections::BTreeMap;
+use std::fmt;
 
-pub trait Metrics {
+// why a metric could not be calculated
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetricError {
+    // request failed or the API answered with an unexpected error
+    Network(String),
+    // missing or rejected token, or no access to the repository
+    Auth(String),
+    // package or repository does not exist
+    NotFound(String),
+    // API budget exhausted until `reset` (unix time in seconds), None if the API did not say
+    RateLimited {
+        resource: String,
+        reset: Option<u64>,
+    },
+    // response could not be read
+    Parse(String),
+    // url is not a GitHub repository or npm package
+    Unsupported(String),
+}
+
+impl fmt::Display for MetricError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetricError::Network(e) => write!(f, "network error: {}", e),
+            MetricError::Auth(e) => write!(f, "authentication failed: {}", e),
+            MetricError::NotFound(e) => write!(f, "not found: {}", e),
+            MetricError::RateLimited {
+                resource,
+                reset: Some(reset),
+            } => write!(f, "{} rate limit exceeded, resets at {}", resource, reset),
+            MetricError::RateLimited {
+                resource,
+                reset: None,
+            } => write!(f, "{} rate limit exceeded", resource),
+            MetricError::Parse(e) => write!(f, "invalid response: {}", e),
+            MetricError::Unsupported(e) => write!(f, "unsupported: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for MetricError {}
+
+impl From<HttpError> for MetricError {
+    fn from(e: HttpError) -> Self {
+        match e {
+            HttpError::Network(_) | HttpError::NotRecorded(_) | HttpError::Io(_) => {
+                MetricError::Network(e.to_string())
+            }
+            HttpError::Json(e) => MetricError::Parse(e.to_string()),
+            HttpError::RateLimited { resource, reset } => MetricError::RateLimited {
+                resource,
+                reset: Some(reset),
+            },
+            HttpError::Status { status: 401, .. } | HttpError::Status { status: 403, .. } => {
+                MetricError::Auth(e.to_string())
+            }
+            HttpError::Status { status: 404, .. } => MetricError::NotFound(e.to_string()),
+            HttpError::Status { .. } => MetricError::Network(e.to_string()),
+            // GitHub sets the type of GraphQL errors
+            HttpError::Graphql(ref errors) => match errors.first().and_then(|e| e.kind.as_deref()) {
+                Some("NOT_FOUND") => MetricError::NotFound(e.to_string()),
+                Some("FORBIDDEN") => MetricError::Auth(e.to_string()),
+                // without rate limit headers, see graphql::decode
+                Some("RATE_LIMITED") => MetricError::RateLimited {
+                    resource: "graphql".to_string(),
+                    reset: None,
+                },
+                _ => MetricError::Network(e.to_string()),
+            },
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, MetricError>;
+
+// result of a metric, Unknown if the metric does not apply to the module or lacks data
+#[derive(Debug, Clone, PartialEq)]
+pub enum Score {
+    Known(f64),
+    Unknown(String),
+}
+
+impl Score {
+    pub fn value(&self) -> Option<f64> {
+        match self {
+            Score::Known(v) => Some(*v),
+            Score::Unknown(_) => None,
+        }
+    }
+}
+
+// score of a metric with what it was calculated from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evaluation {
+    pub score: Score,
+    // raw inputs, e.g. issue counts or the SPDX id of the license
+    pub evidence: BTreeMap<String, serde_json::Value>,
+    // parameters of the formula
+    pub parameters: BTreeMap<String, f64>,
+    // human readable, the reason if the score is unknown
+    pub explanation: String,
+}
+
+impl Evaluation {
+    pub fn known(score: f64, explanation: impl Into<String>) -> Evaluation {
+        Evaluation {
+            score: Score::Known(score),
+            evidence: BTreeMap::new(),
+            parameters: BTreeMap::new(),
+            explanation: explanation.into(),
+        }
+    }
+
+    pub fn unknown(reason: impl Into<String>) -> Evaluation {
+        let reason = reason.into();
+        Evaluation {
+            score: Score::Unknown(reason.clone()),
+            evidence: BTreeMap::new(),
+            parameters: BTreeMap::new(),
+            explanation: reason,
+        }
+    }
+
+    pub fn evidence(mut self, name: &str, value: impl Into<serde_json::Value>) -> Evaluation {
+        self.evidence.insert(name.to_string(), value.into());
+        self
+    }
+
+    pub fn parameter(mut self, name: &str, value: f64) -> Evaluation {
+        self.parameters.insert(name.to_string(), value);
+        self
+    }
+
+    // parameters of the curve the score was mapped with
+    pub fn curve(mut self, curve: &Curve) -> Evaluation {
+        for (name, value) in curve.parameters() {
+            self = self.parameter(&name, value);
+        }
+        self
+    }
+
+    pub fn value(&self) -> Option<f64> {
+        self.score.value()
+    }
+
+    // score, explanation, evidence and parameters as json, the score is null if unknown
+    pub fn explain(&self) -> serde_json::Value {
+        serde_json::json!({
+            "score": self.value(),
+            "explanation": self.explanation,
+            "evidence": self.evidence,
+            "parameters": self.parameters,
+        })
+    }
+}
+
+impl From<Score> for Evaluation {
+    fn from(score: Score) -> Self {
+        match score {
+            Score::Known(v) => Evaluation::known(v, ""),
+            Score::Unknown(reason) => Evaluation::unknown(reason),
+        }
+    }
+}
+
+// modules are scored on several threads
+pub trait Metrics: Send + Sync {
     // calculate metrics of module
-    // returns value in range [0, 1]
+    // returns value in range [0, 1], or Unknown with the reason,
+    // along with the inputs and parameters used and an explanation
 
     // ramp up time for engineers to learn module
-    fn ramp_up_time(&self) -> f64;
+    fn ramp_up_time(&self) -> Result<Evaluation>;
 
     // correctness of module
-    fn correctness(&self) -> f64;
+    fn correctness(&self) -> Result<Evaluation>;
 
     // whether there are enough maintainers for module
-    fn bus_factor(&self) -> f64;
+    fn bus_factor(&self) -> Result<Evaluation>;
 
     // responsiveness of module maintainers fixing bugs
-    fn responsiveness(&self) -> f64;
+    fn responsiveness(&self) -> Result<Evaluation>;
 
     // whether module is compatible with LGPLv2.1
-    fn compatibility(&self) -> f64;
+    fn compatibility(&self) -> Result<Evaluation>;
 
     // new reviewed_code metric for part 2
-    fn reviewed_code(&self) -> f64;
+    fn reviewed_code(&self) -> Result<Evaluation>;
 
     // new pinning_practice metric for part 2
-    fn pinning_practice(&self) -> f64;
+    fn pinning_practice(&self) -> Result<Evaluation>;
 
     // formulas for calculating metrics
-    fn calc_ramp_up_time(lines: u32) -> f64
+    fn calc_ramp_up_time(lines: u32, curve: &Curve) -> f64
     where
         Self: Sized,
     {
-        let mut x = lines as f64;
-        x = x / 150.0 * 0.7;
-        let normal = Normal::new(0.0, 1.0).unwrap();
-
-        normal.pdf(x) * x.sqrt() / 0.2613
+        curve.apply(lines as f64)
     }
 
     fn calc_correctness(all: u32, closed: u32) -> f64
@@ -75,45 +253,123 @@ pub trait Metrics {
     }
 }
 
+// scores of all metrics of a module, by metric id in registry order
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scores {
+    pub evaluations: Vec<(String, Evaluation)>,
+}
+
+// net score of a module
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NetScore {
+    pub score: f64,
+    // fraction of the total weight carried by known metrics, in range [0, 1]
+    pub coverage: f64,
+}
+
+impl Scores {
+    // metrics are calculated one after another from the facts fetched for the module, packages
+    // are scored concurrently by the workers of score_many
+    // the first failing metric fails the module
+    pub fn calculate(
+        project: &dyn Metrics,
+        facts: &RepositoryFacts,
+        registry: &Registry,
+    ) -> Result<Scores> {
+        let evaluations = registry
+            .metrics()
+            .iter()
+            .map(|metric| (metric.compute)(project, facts).map(|e| (metric.id.clone(), e)))
+            .collect::<Result<Vec<(String, Evaluation)>>>()?;
+        Ok(Scores { evaluations })
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Evaluation> {
+        self.evaluations
+            .iter()
+            .find(|(i, _)| i == id)
+            .map(|(_, e)| e)
+    }
+
+    // weighted average of the known scores, weights of unknown scores are left out
+    pub fn net_score(&self, weights: &Weights) -> NetScore {
+        let weighted: Vec<(&Evaluation, f64)> = self
+            .evaluations
+            .iter()
+            .map(|(id, e)| (e, weights.get(id)))
+            .collect();
+        let total: f64 = weighted.iter().map(|(_, w)| w).sum();
+        let known: Vec<(f64, f64)> = weighted
+            .iter()
+            .filter_map(|(s, w)| s.value().map(|v| (v, *w)))
+            .collect();
+        let known_weight: f64 = known.iter().map(|(_, w)| w).sum();
+
+        if known_weight == 0.0 {
+            return NetScore {
+                score: 0.0,
+                coverage: 0.0,
+            };
+        }
+        NetScore {
+            score: known.iter().map(|(v, w)| v * w).sum::<f64>() / known_weight,
+            coverage: known_weight / total,
+        }
+    }
+
+    // explanation of every score by metric id
+    pub fn explain(&self) -> serde_json::Value {
+        serde_json::Value::Object(
+            self.evaluations
+                .iter()
+                .map(|(id, e)| (id.clone(), e.explain()))
+                .collect(),
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::metrics::github::offline_facts;
 
     struct TestMetrics;
     impl Metrics for TestMetrics {
-        fn ramp_up_time(&self) -> f64 {
-            0.0
+        fn ramp_up_time(&self) -> Result<Evaluation> {
+            Ok(Score::Known(0.0).into())
         }
-        fn correctness(&self) -> f64 {
-            0.0
+        fn correctness(&self) -> Result<Evaluation> {
+            Ok(Score::Known(0.0).into())
         }
-        fn bus_factor(&self) -> f64 {
-            0.0
+        fn bus_factor(&self) -> Result<Evaluation> {
+            Ok(Score::Known(0.0).into())
         }
-        fn reviewed_code(&self) -> f64 {
-            0.0
+        fn reviewed_code(&self) -> Result<Evaluation> {
+            Ok(Score::Known(0.0).into())
         }
-        fn responsiveness(&self) -> f64 {
-            0.0
+        fn responsiveness(&self) -> Result<Evaluation> {
+            Ok(Score::Known(0.0).into())
         }
-        fn compatibility(&self) -> f64 {
-            0.0
+        fn compatibility(&self) -> Result<Evaluation> {
+            Ok(Score::Known(0.0).into())
         }
-        fn pinning_practice(&self) -> f64 {
-            0.0
+        fn pinning_practice(&self) -> Result<Evaluation> {
+            Ok(Score::Known(0.0).into())
         }
     }
 
     #[test]
     fn ramp_up_time_edge_cases() {
-        assert!(TestMetrics::calc_ramp_up_time(0) == 0.0);
-        assert!(TestMetrics::calc_ramp_up_time(u32::MAX) == 0.0);
-        assert!(TestMetrics::calc_ramp_up_time(1000) <= 0.1);
+        let ramp_up = curve::Curves::default().ramp_up;
+        assert!(TestMetrics::calc_ramp_up_time(0, &ramp_up) == 0.0);
+        assert!(TestMetrics::calc_ramp_up_time(u32::MAX, &ramp_up) == 0.0);
+        assert!(TestMetrics::calc_ramp_up_time(1000, &ramp_up) <= 0.1);
     }
 
     #[test]
     fn ramp_up_time_max() {
-        assert!(TestMetrics::calc_ramp_up_time(150) >= 0.99);
+        let ramp_up = curve::Curves::default().ramp_up;
+        assert!(TestMetrics::calc_ramp_up_time(150, &ramp_up) >= 0.99);
     }
 
     #[test]
@@ -137,4 +393,180 @@ mod tests {
         assert!(TestMetrics::calc_compatibility("notMIT") == 0.0);
         assert!(TestMetrics::calc_compatibility("") == 0.0);
     }
+
+    struct Ordered;
+    impl Metrics for Ordered {
+        fn ramp_up_time(&self) -> Result<Evaluation> {
+            Ok(Score::Known(0.1).into())
+        }
+        fn correctness(&self) -> Result<Evaluation> {
+            Ok(Score::Known(0.2).into())
+        }
+        fn bus_factor(&self) -> Result<Evaluation> {
+            Ok(Score::Known(0.3).into())
+        }
+        fn responsiveness(&self) -> Result<Evaluation> {
+            Ok(Score::Known(0.4).into())
+        }
+        fn compatibility(&self) -> Result<Evaluation> {
+            Ok(Score::Known(0.5).into())
+        }
+        fn reviewed_code(&self) -> Result<Evaluation> {
+            Ok(Score::Known(0.6).into())
+        }
+        fn pinning_practice(&self) -> Result<Evaluation> {
+            Ok(Score::Unknown("no package.json".to_string()).into())
+        }
+    }
+
+    #[test]
+    fn scores_match_metrics() {
+        let facts = offline_facts(serde_json::json!({}));
+        let s = Scores::calculate(&Ordered, &facts, &Registry::default()).unwrap();
+        let id = |id: &str| id.to_string();
+        assert_eq!(
+            Scores {
+                evaluations: vec![
+                    (id("ramp_up"), Score::Known(0.1).into()),
+                    (id("correctness"), Score::Known(0.2).into()),
+                    (id("bus_factor"), Score::Known(0.3).into()),
+                    (id("reviewed_code"), Score::Known(0.6).into()),
+                    (id("responsiveness"), Score::Known(0.4).into()),
+                    (
+                        id("pinning_practice"),
+                        Score::Unknown("no package.json".to_string()).into()
+                    ),
+                    (id("license"), Score::Known(0.5).into()),
+                ],
+            },
+            s
+        );
+        assert_eq!(Some(0.5), s.get("license").unwrap().value());
+        assert!(s.get("missing").is_none());
+    }
+
+    #[test]
+    fn net_score_renormalized() {
+        let known = |v| Evaluation::known(v, "");
+        let unknown = || Evaluation::unknown("no data");
+        let mut s = Scores {
+            evaluations: Registry::default()
+                .metrics()
+                .iter()
+                .map(|m| (m.id.clone(), known(1.0)))
+                .collect(),
+        };
+        let license = s.evaluations.len() - 1;
+        let w = Weights::default();
+        assert!((s.net_score(&w).score - 1.0).abs() < 1e-9);
+        assert!((s.net_score(&w).coverage - 1.0).abs() < 1e-9);
+
+        // unknown is not the same as zero
+        s.evaluations[license].1 = unknown();
+        assert!((s.net_score(&w).score - 1.0).abs() < 1e-9);
+        assert!((s.net_score(&w).coverage - 0.8 / 1.2).abs() < 1e-9);
+        s.evaluations[license].1 = known(0.0);
+        assert!((s.net_score(&w).score - 0.8 / 1.2).abs() < 1e-9);
+
+        for (_, score) in s.evaluations.iter_mut() {
+            *score = unknown();
+        }
+        assert_eq!(
+            NetScore {
+                score: 0.0,
+                coverage: 0.0
+            },
+            s.net_score(&w)
+        );
+    }
+
+    struct Failing;
+    impl Metrics for Failing {
+        fn ramp_up_time(&self) -> Result<Evaluation> {
+            Ok(Score::Known(1.0).into())
+        }
+        fn correctness(&self) -> Result<Evaluation> {
+            Err(MetricError::NotFound("owner/repo".to_string()))
+        }
+        fn bus_factor(&self) -> Result<Evaluation> {
+            Err(MetricError::Network("timeout".to_string()))
+        }
+        fn responsiveness(&self) -> Result<Evaluation> {
+            Ok(Score::Known(1.0).into())
+        }
+        fn compatibility(&self) -> Result<Evaluation> {
+            Ok(Score::Known(1.0).into())
+        }
+        fn reviewed_code(&self) -> Result<Evaluation> {
+            Ok(Score::Known(1.0).into())
+        }
+        fn pinning_practice(&self) -> Result<Evaluation> {
+            Ok(Score::Known(1.0).into())
+        }
+    }
+
+    #[test]
+    fn scores_first_error() {
+        assert_eq!(
+            Err(MetricError::NotFound("owner/repo".to_string())),
+            Scores::calculate(
+                &Failing,
+                &offline_facts(serde_json::json!({})),
+                &Registry::default(),
+            )
+        );
+    }
+
+    #[test]
+    fn evaluation_explained() {
+        let e = Evaluation::known(0.5, "3 collaborators")
+            .evidence("collaborators", 3)
+            .parameter("scale", 13.0);
+        assert_eq!(
+            serde_json::json!({
+                "score": 0.5,
+                "explanation": "3 collaborators",
+                "evidence": { "collaborators": 3 },
+                "parameters": { "scale": 13.0 }
+            }),
+            e.explain()
+        );
+        assert_eq!(
+            serde_json::Value::Null,
+            Evaluation::unknown("no license found").explain()["score"]
+        );
+    }
+
+    #[test]
+    fn http_errors_classified() {
+        let status = |status| HttpError::Status {
+            status,
+            url: "https://api.github.com/graphql".to_string(),
+        };
+        assert!(matches!(MetricError::from(status(401)), MetricError::Auth(_)));
+        assert!(matches!(MetricError::from(status(404)), MetricError::NotFound(_)));
+        assert!(matches!(MetricError::from(status(502)), MetricError::Network(_)));
+        assert_eq!(
+            MetricError::RateLimited {
+                resource: "core".to_string(),
+                reset: Some(10)
+            },
+            MetricError::from(HttpError::RateLimited {
+                resource: "core".to_string(),
+                reset: 10
+            })
+        );
+        let not_found = HttpError::Graphql(vec![crate::http::graphql::GraphqlError {
+            message: "Could not resolve to a Repository".to_string(),
+            kind: Some("NOT_FOUND".to_string()),
+        }]);
+        assert!(matches!(MetricError::from(not_found), MetricError::NotFound(_)));
+        let rate_limited = MetricError::from(HttpError::Graphql(vec![
+            crate::http::graphql::GraphqlError {
+                message: "API rate limit exceeded".to_string(),
+                kind: Some("RATE_LIMITED".to_string()),
+            },
+        ]));
+        assert_eq!("graphql rate limit exceeded", rate_limited.to_string());
+    }
 }
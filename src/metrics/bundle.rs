@@ -0,0 +1,146 @@
+// facts of several modules saved to a file, for scoring on a machine without network access
+//
+// Written by the collect command and read by score --from-bundle:
+//
+//   {"version": 1, "created_at": "2024-03-01T12:00:00+00:00",
+//    "packages": [{"url": "https://www.npmjs.com/package/left-pad",
+//                  "collected_at": "2024-03-01T11:59:58+00:00",
+//                  "facts": {"repository": {...}, "search": {...}}}]}
+//
+// Timestamps are RFC 3339 in UTC. Facts such as the pull requests of the last year
+// are relative to collected_at, not to the time the bundle is scored.
+use crate::metrics::facts::RepositoryFacts;
+use chrono::offset::Utc;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+pub const BUNDLE_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum BundleError {
+    Io(std::io::Error),
+    Parse(String),
+    // written by another version of the tool
+    Version(u32),
+}
+
+impl fmt::Display for BundleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BundleError::Io(e) => write!(f, "failed to read bundle: {}", e),
+            BundleError::Parse(e) => write!(f, "invalid bundle: {}", e),
+            BundleError::Version(v) => write!(
+                f,
+                "bundle version {} is not supported, expected {}",
+                v, BUNDLE_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BundleError {}
+
+impl From<std::io::Error> for BundleError {
+    fn from(e: std::io::Error) -> Self {
+        BundleError::Io(e)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bundle {
+    pub version: u32,
+    pub created_at: String,
+    pub packages: Vec<BundledFacts>,
+}
+
+// facts of one module with where and when they were collected
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundledFacts {
+    // url of the module as given to collect
+    pub url: String,
+    pub collected_at: String,
+    pub facts: RepositoryFacts,
+}
+
+impl Default for Bundle {
+    fn default() -> Self {
+        Bundle {
+            version: BUNDLE_VERSION,
+            created_at: Utc::now().to_rfc3339(),
+            packages: Vec::new(),
+        }
+    }
+}
+
+impl Bundle {
+    // add facts collected just now
+    pub fn push(&mut self, url: &str, facts: RepositoryFacts) {
+        self.packages.push(BundledFacts {
+            url: url.to_string(),
+            collected_at: Utc::now().to_rfc3339(),
+            facts,
+        });
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), BundleError> {
+        let json =
+            serde_json::to_string_pretty(self).map_err(|e| BundleError::Parse(e.to_string()))?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Bundle, BundleError> {
+        Bundle::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Bundle, BundleError> {
+        // check the version first, other versions may have another layout
+        let version: serde_json::Value =
+            serde_json::from_str(text).map_err(|e| BundleError::Parse(e.to_string()))?;
+        match version["version"].as_u64() {
+            Some(v) if v == BUNDLE_VERSION as u64 => {}
+            Some(v) => return Err(BundleError::Version(v as u32)),
+            None => return Err(BundleError::Parse("version is missing".to_string())),
+        }
+        serde_json::from_value(version).map_err(|e| BundleError::Parse(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::facts::Collector;
+    use crate::metrics::github::{facts_response, offline_github};
+
+    #[test]
+    fn saved_and_loaded() {
+        let facts = offline_github(&facts_response(
+            serde_json::json!({ "licenseInfo": { "spdxId": "MIT" } }),
+        ))
+        .collect()
+        .unwrap();
+        let mut bundle = Bundle::default();
+        bundle.push("https://github.com/owner/repo", facts);
+
+        let path = std::env::temp_dir().join(format!("bundle-{}.json", std::process::id()));
+        bundle.save(&path).unwrap();
+        let loaded = Bundle::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(bundle, loaded);
+        assert!(chrono::DateTime::parse_from_rfc3339(&loaded.packages[0].collected_at).is_ok());
+    }
+
+    #[test]
+    fn version_checked() {
+        assert!(matches!(
+            Bundle::parse("{\"version\":2,\"created_at\":\"\",\"packages\":[]}"),
+            Err(BundleError::Version(2))
+        ));
+        assert!(matches!(
+            Bundle::parse("{\"packages\":[]}"),
+            Err(BundleError::Parse(_))
+        ));
+        assert!(Bundle::parse("{\"version\":1,\"created_at\":\"\",\"packages\":[]}").is_ok());
+    }
+}
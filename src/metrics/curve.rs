@@ -0,0 +1,397 @@
+// transfer functions mapping a raw count to a score in [0, 1]
+//
+// Declared in the [curves] table of the config file, see profile.rs:
+//
+//   [curves.responsiveness]
+//   type = "normal_cdf"
+//   scale = 13.0
+//   offset = 2.0
+use serde::{Deserialize, Serialize};
+use statrs::distribution::{Continuous, ContinuousCDF, Normal};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Curve {
+    // 1 / (1 + e^(-steepness * (x - midpoint)))
+    Logistic {
+        midpoint: f64,
+        steepness: f64,
+    },
+    // standard normal CDF of x / scale - offset
+    NormalCdf {
+        scale: f64,
+        offset: f64,
+    },
+    // linear between (x, y) points sorted by x, constant outside of them
+    PiecewiseLinear {
+        points: Vec<[f64; 2]>,
+    },
+    // (x - offset) / (x - offset + half), 0 below offset and 0.5 at offset + half
+    Saturating {
+        offset: f64,
+        half: f64,
+    },
+    // φ(u)√u / norm of u = shape x / at, rises to 1 at about x = at and falls back towards 0
+    // the defaults are the constants of the ramp up formula of part 1
+    Peak {
+        at: f64,
+        #[serde(default = "default_shape")]
+        shape: f64,
+        #[serde(default = "default_norm")]
+        norm: f64,
+    },
+}
+
+fn default_shape() -> f64 {
+    0.7
+}
+
+fn default_norm() -> f64 {
+    0.2613
+}
+
+impl Curve {
+    pub fn apply(&self, x: f64) -> f64 {
+        let normal = Normal::new(0.0, 1.0).unwrap();
+        let y = match self {
+            Curve::Logistic {
+                midpoint,
+                steepness,
+            } => 1.0 / (1.0 + (-steepness * (x - midpoint)).exp()),
+            Curve::NormalCdf { scale, offset } => normal.cdf(x / scale - offset),
+            Curve::PiecewiseLinear { points } => {
+                let (first, last) = (points[0], points[points.len() - 1]);
+                if x <= first[0] {
+                    first[1]
+                } else if x >= last[0] {
+                    last[1]
+                } else {
+                    let i = points.iter().position(|p| p[0] > x).unwrap();
+                    let ([x0, y0], [x1, y1]) = (points[i - 1], points[i]);
+                    y0 + (y1 - y0) * (x - x0) / (x1 - x0)
+                }
+            }
+            Curve::Saturating { offset, half } => {
+                let x = (x - offset).max(0.0);
+                x / (x + half)
+            }
+            Curve::Peak { at, shape, norm } => {
+                let u = (x / at * shape).max(0.0);
+                normal.pdf(u) * u.sqrt() / norm
+            }
+        };
+        if y.is_nan() {
+            0.0
+        } else {
+            y.clamp(0.0, 1.0)
+        }
+    }
+
+    // error if the parameters do not describe a curve
+    pub fn validate(&self) -> Result<(), String> {
+        let positive = |name: &str, v: f64| {
+            if v.is_finite() && v > 0.0 {
+                Ok(())
+            } else {
+                Err(format!("{} must be positive", name))
+            }
+        };
+        match self {
+            Curve::Logistic {
+                midpoint,
+                steepness,
+            } => {
+                if !midpoint.is_finite() || !steepness.is_finite() || *steepness == 0.0 {
+                    return Err("midpoint and a non-zero steepness are required".to_string());
+                }
+                Ok(())
+            }
+            Curve::NormalCdf { scale, offset } => {
+                positive("scale", *scale)?;
+                if !offset.is_finite() {
+                    return Err("offset must be a number".to_string());
+                }
+                Ok(())
+            }
+            Curve::PiecewiseLinear { points } => {
+                if points.is_empty() {
+                    return Err("at least one point is required".to_string());
+                }
+                if points
+                    .iter()
+                    .any(|[x, y]| !x.is_finite() || !(0.0..=1.0).contains(y))
+                {
+                    return Err("points must have y in [0, 1]".to_string());
+                }
+                if points.windows(2).any(|w| w[0][0] >= w[1][0]) {
+                    return Err("points must be sorted by x".to_string());
+                }
+                Ok(())
+            }
+            Curve::Saturating { offset, half } => {
+                positive("half", *half)?;
+                if !offset.is_finite() {
+                    return Err("offset must be a number".to_string());
+                }
+                Ok(())
+            }
+            Curve::Peak { at, shape, norm } => {
+                positive("at", *at)?;
+                positive("shape", *shape)?;
+                positive("norm", *norm)
+            }
+        }
+    }
+
+    // named parameters, recorded with the evidence of a score
+    pub fn parameters(&self) -> Vec<(String, f64)> {
+        match self {
+            Curve::Logistic {
+                midpoint,
+                steepness,
+            } => vec![
+                ("midpoint".to_string(), *midpoint),
+                ("steepness".to_string(), *steepness),
+            ],
+            Curve::NormalCdf { scale, offset } => vec![
+                ("scale".to_string(), *scale),
+                ("offset".to_string(), *offset),
+            ],
+            Curve::PiecewiseLinear { points } => points
+                .iter()
+                .enumerate()
+                .flat_map(|(i, [x, y])| [(format!("x{}", i), *x), (format!("y{}", i), *y)])
+                .collect(),
+            Curve::Saturating { offset, half } => {
+                vec![("offset".to_string(), *offset), ("half".to_string(), *half)]
+            }
+            Curve::Peak { at, shape, norm } => vec![
+                ("at".to_string(), *at),
+                ("shape".to_string(), *shape),
+                ("norm".to_string(), *norm),
+            ],
+        }
+    }
+}
+
+impl fmt::Display for Curve {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Curve::Logistic {
+                midpoint,
+                steepness,
+            } => write!(
+                f,
+                "logistic curve at {} with steepness {}",
+                midpoint, steepness
+            ),
+            Curve::NormalCdf { scale, offset } => {
+                write!(f, "normal CDF of n/{}-{}", scale, offset)
+            }
+            Curve::PiecewiseLinear { points } => {
+                let points: Vec<String> = points
+                    .iter()
+                    .map(|[x, y]| format!("({}, {})", x, y))
+                    .collect();
+                write!(f, "piecewise linear through {}", points.join(", "))
+            }
+            Curve::Saturating { offset, half } => {
+                write!(f, "saturating curve, 0.5 at {}", offset + half)
+            }
+            Curve::Peak { at, .. } => write!(f, "highest at {}", at),
+        }
+    }
+}
+
+// curves of the metrics scored from counts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Curves {
+    // lines of the README
+    pub ramp_up: Curve,
+    // collaborators
+    pub bus_factor: Curve,
+    // pull requests updated in the last year
+    pub responsiveness: Curve,
+}
+
+impl Default for Curves {
+    fn default() -> Self {
+        Curves {
+            ramp_up: Curve::Peak {
+                at: 150.0,
+                shape: default_shape(),
+                norm: default_norm(),
+            },
+            // 2n/(n+1) - 1
+            bus_factor: Curve::Saturating {
+                offset: 1.0,
+                half: 2.0,
+            },
+            responsiveness: Curve::NormalCdf {
+                scale: 13.0,
+                offset: 2.0,
+            },
+        }
+    }
+}
+
+impl Curves {
+    pub fn validate(&self) -> Result<(), (String, String)> {
+        for (name, curve) in [
+            ("ramp_up", &self.ramp_up),
+            ("bus_factor", &self.bus_factor),
+            ("responsiveness", &self.responsiveness),
+        ] {
+            curve
+                .validate()
+                .map_err(|reason| (name.to_string(), reason))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn logistic() {
+        let c = Curve::Logistic {
+            midpoint: 10.0,
+            steepness: 0.5,
+        };
+        assert!(close(0.5, c.apply(10.0)));
+        assert!(c.apply(0.0) < 0.01);
+        assert!(c.apply(30.0) > 0.99);
+        assert!(c.apply(9.0) < c.apply(11.0));
+        assert!(c.validate().is_ok());
+        let flat = Curve::Logistic {
+            midpoint: 10.0,
+            steepness: 0.0,
+        };
+        assert!(flat.validate().is_err());
+    }
+
+    #[test]
+    fn normal_cdf() {
+        let c = Curve::NormalCdf {
+            scale: 13.0,
+            offset: 2.0,
+        };
+        assert!(close(0.5, c.apply(26.0)));
+        assert!(c.apply(0.0) < 0.05);
+        assert!(c.apply(100.0) > 0.99);
+        let zero = Curve::NormalCdf {
+            scale: 0.0,
+            offset: 2.0,
+        };
+        assert!(zero.validate().is_err());
+    }
+
+    #[test]
+    fn piecewise_linear() {
+        let c = Curve::PiecewiseLinear {
+            points: vec![[0.0, 0.0], [10.0, 0.5], [20.0, 1.0]],
+        };
+        assert!(close(0.0, c.apply(-5.0)));
+        assert!(close(0.25, c.apply(5.0)));
+        assert!(close(0.5, c.apply(10.0)));
+        assert!(close(0.75, c.apply(15.0)));
+        assert!(close(1.0, c.apply(50.0)));
+        assert!(c.validate().is_ok());
+
+        let unsorted = Curve::PiecewiseLinear {
+            points: vec![[10.0, 0.0], [0.0, 1.0]],
+        };
+        assert!(unsorted.validate().is_err());
+        let out_of_range = Curve::PiecewiseLinear {
+            points: vec![[0.0, 2.0]],
+        };
+        assert!(out_of_range.validate().is_err());
+        let empty = Curve::PiecewiseLinear { points: vec![] };
+        assert!(empty.validate().is_err());
+    }
+
+    #[test]
+    fn saturating() {
+        let c = Curves::default().bus_factor;
+        // same as 2n/(n+1) - 1, but not negative
+        for n in 1..50 {
+            let n = n as f64;
+            assert!(close(2.0 * n / (n + 1.0) - 1.0, c.apply(n)));
+        }
+        assert!(close(0.0, c.apply(0.0)));
+        let zero = Curve::Saturating {
+            offset: 0.0,
+            half: 0.0,
+        };
+        assert!(zero.validate().is_err());
+    }
+
+    #[test]
+    fn peak() {
+        let c = Curves::default().ramp_up;
+        assert!(c.apply(150.0) >= 0.99);
+        assert!(c.apply(140.0) < c.apply(150.0));
+        assert!(c.apply(160.0) < 1.0);
+        assert!(c.apply(1000.0) <= 0.1);
+        assert!(close(0.0, c.apply(0.0)));
+        assert!(close(0.0, c.apply(u32::MAX as f64)));
+        let peak = |at: f64, shape: f64, norm: f64| Curve::Peak { at, shape, norm };
+        assert!(peak(-1.0, 0.7, 0.2613).validate().is_err());
+        assert!(peak(150.0, 0.0, 0.2613).validate().is_err());
+        assert!(peak(150.0, 0.7, f64::NAN).validate().is_err());
+    }
+
+    #[test]
+    fn peak_recalibrated() {
+        // shape and norm default to the part 1 constants
+        let c: Curve = toml::from_str("type = \"peak\"\nat = 150").unwrap();
+        assert_eq!(Curves::default().ramp_up, c);
+        let names: Vec<String> = c.parameters().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(vec!["at", "shape", "norm"], names);
+
+        // a lower norm reaches 1 on a wider range of lines
+        let c: Curve =
+            toml::from_str("type = \"peak\"\nat = 150\nshape = 0.7\nnorm = 0.2").unwrap();
+        assert!(close(1.0, c.apply(100.0)));
+        assert!(Curves::default().ramp_up.apply(100.0) < 1.0);
+    }
+
+    #[test]
+    fn default_ramp_up_unchanged() {
+        // x / 150 * 0.7, φ(x)√x / 0.2613 before curves were configurable
+        let normal = Normal::new(0.0, 1.0).unwrap();
+        let baseline = |lines: f64| {
+            let x = lines / 150.0 * 0.7;
+            (normal.pdf(x) * x.sqrt() / 0.2613).min(1.0)
+        };
+        let c = Curves::default().ramp_up;
+        for lines in [
+            0, 1, 10, 50, 100, 149, 150, 151, 152, 200, 300, 500, 1000, 5000,
+        ] {
+            let lines = lines as f64;
+            assert_eq!(baseline(lines), c.apply(lines), "{} lines", lines);
+        }
+    }
+
+    #[test]
+    fn declared_in_toml() {
+        let curves: Curves = toml::from_str(
+            "[ramp_up]\ntype = \"piecewise_linear\"\npoints = [[0, 0], [100, 1]]\n\
+             [responsiveness]\ntype = \"logistic\"\nmidpoint = 20\nsteepness = 0.3\n",
+        )
+        .unwrap();
+        assert!(close(0.5, curves.ramp_up.apply(50.0)));
+        assert!(close(0.5, curves.responsiveness.apply(20.0)));
+        // not declared, default curve
+        assert_eq!(Curves::default().bus_factor, curves.bus_factor);
+        assert!(toml::from_str::<Curves>("[ramp_up]\ntype = \"cubic\"\n").is_err());
+    }
+}
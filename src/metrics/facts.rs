@@ -0,0 +1,95 @@
+// facts about a repository, collected once and scored without network access
+//
+// The document has the shape of the GraphQL response of Github::facts and can be
+// saved as JSON and scored later with other weights or curves, see scoring.rs.
+use crate::http::graphql::{Edges, TotalCount};
+use crate::metrics::identity::{self, PackageIdentity};
+use crate::metrics::Result;
+use serde::{Deserialize, Serialize};
+
+// gathers the facts of a module, e.g. from the GitHub API
+pub trait Collector: Send + Sync {
+    fn collect(&self) -> Result<RepositoryFacts>;
+
+    // name and version of the package, see identity.rs
+    fn identity(&self) -> Result<PackageIdentity> {
+        Ok(identity::from_facts(&self.collect()?))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RepositoryFacts {
+    pub repository: Repository,
+    // pull requests updated in the last year
+    pub search: IssueCount,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Repository {
+    // empty in facts saved before it was collected
+    #[serde(default)]
+    pub name: String,
+    // issues exclude pull requests in GraphQL
+    pub issues: TotalCount,
+    pub closed_issues: TotalCount,
+    // last 100 pull requests
+    pub pull_requests: Edges<PullRequest>,
+    pub license_info: Option<License>,
+    pub mentionable_users: TotalCount,
+    pub releases: Edges<Release>,
+    // latest tag by commit date
+    #[serde(default)]
+    pub tags: Edges<Tag>,
+    // root of the default branch, None for an empty repository
+    pub tree: Option<Tree>,
+    // None if the file does not exist, text None if it is binary
+    pub readme: Option<Blob>,
+    pub package_json: Option<Blob>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IssueCount {
+    pub issue_count: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PullRequest {
+    pub reviews: TotalCount,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct License {
+    pub spdx_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Release {
+    pub tag_name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tag {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tree {
+    pub entries: Vec<TreeEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TreeEntry {
+    pub name: String,
+    // blob, tree or commit
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Blob {
+    pub text: Option<String>,
+}
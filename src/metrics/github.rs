@@ -1,535 +1,899 @@
-use crate::metrics::Metrics;
+use crate::http::graphql;
+use crate::http::{self, link, HttpRequest, HttpResponse, Transport};
+use crate::metrics::curve::Curves;
+use crate::metrics::facts::{Collector, RepositoryFacts};
+use crate::metrics::scoring;
+use crate::metrics::{Evaluation, MetricError, Metrics, Result};
+#[cfg(test)]
+use crate::metrics::Score;
 use chrono::offset::Utc;
-use log::{debug, info};
-use reqwest::header;
-use statrs::distribution::{ContinuousCDF, Normal};
-use std::io::BufRead;
-use pyo3::{prelude::*};
-use serde::{Deserialize};
+use log::info;
+use serde::de::DeserializeOwned;
+use std::sync::{Arc, Mutex, OnceLock};
+
+// GitHub endpoints used by the client
+// defaults to github.com, but can point at a GitHub Enterprise Server instance
+// or a local stand-in server through environment variables:
+//   GITHUB_HOST        comma separated web hosts accepted in repository urls
+//   GITHUB_API_URL     root of the REST API
+//   GITHUB_GRAPHQL_URL GraphQL endpoint
+#[derive(Debug, Clone, PartialEq)]
+pub struct GithubConfig {
+    pub hosts: Vec<String>,
+    pub api_url: String,
+    pub graphql_url: String,
+    pub token: Option<String>,
+}
+
+impl GithubConfig {
+    // endpoints for a given web host, following the GitHub Enterprise Server layout
+    // see: https://docs.github.com/en/enterprise-server@3.8/rest/guides/getting-started-with-the-rest-api
+    pub fn for_host(host: &str) -> GithubConfig {
+        let (api_url, graphql_url) = if host == "github.com" {
+            (
+                "https://api.github.com".to_string(),
+                "https://api.github.com/graphql".to_string(),
+            )
+        } else {
+            (
+                format!("https://{}/api/v3", host),
+                format!("https://{}/api/graphql", host),
+            )
+        };
+
+        GithubConfig {
+            hosts: vec![host.to_string()],
+            api_url,
+            graphql_url,
+            token: None,
+        }
+    }
+
+    // read configuration from the environment
+    pub fn from_env() -> GithubConfig {
+        let hosts: Vec<String> = std::env::var("GITHUB_HOST")
+            .unwrap_or_default()
+            .split(',')
+            .map(|h| h.trim().to_lowercase())
+            .filter(|h| !h.is_empty())
+            .collect();
+
+        let mut config =
+            GithubConfig::for_host(hosts.first().map(String::as_str).unwrap_or("github.com"));
+        config.token = std::env::var("GITHUB_TOKEN").ok();
+        if !hosts.is_empty() {
+            config.hosts = hosts;
+        }
+        if let Ok(api_url) = std::env::var("GITHUB_API_URL") {
+            config.api_url = api_url.trim_end_matches('/').to_string();
+        }
+        if let Ok(graphql_url) = std::env::var("GITHUB_GRAPHQL_URL") {
+            config.graphql_url = graphql_url;
+        }
+
+        config
+    }
+
+    // whether the url points at one of the configured GitHub hosts
+    pub fn accepts(&self, url: &reqwest::Url) -> bool {
+        let host = match url.host_str() {
+            Some(host) => host.to_lowercase(),
+            None => return false,
+        };
+        let host_port = url.port().map(|p| format!("{}:{}", host, p));
+
+        self.hosts
+            .iter()
+            .any(|h| *h == host || Some(h) == host_port.as_ref())
+    }
+}
 
 #[derive(Debug)]
 pub struct Github {
     // repository information
     owner: String,
     repo: String,
-    link: String,
+    // package API usage is reported under
+    label: String,
 
     // API-related
-    client: reqwest::blocking::Client,
-}
+    config: GithubConfig,
+    transport: Arc<dyn Transport>,
 
-#[derive(Debug, Deserialize)]
-struct GithubPinningPractice {
-    url: String,
-    name: String,
-}
+    // map counts to scores
+    curves: Curves,
 
-#[derive(Debug, Deserialize)]
-struct PinningPracticePackageJSON {
-    #[serde(rename = "type")]
-    content: Option<String>
+    // fetched on first use, metrics running concurrently wait for the same fetch
+    facts: OnceLock<RepositoryFacts>,
+    fetching: Mutex<()>,
 }
 
+// everything the metrics need about a repository, fetched in one GraphQL query
+// $search finds the pull requests updated in the last year
+const REPOSITORY_FACTS_QUERY: &str = "query($owner: String!, $name: String!, $search: String!) { \
+repository(owner: $owner, name: $name) { \
+name \
+issues { totalCount } \
+closedIssues: issues(states: CLOSED) { totalCount } \
+pullRequests(first: 100, orderBy: {field: CREATED_AT, direction: DESC}) { edges { node { reviews(first: 1) { totalCount } } } } \
+licenseInfo { spdxId } \
+mentionableUsers { totalCount } \
+releases(last: 1) { edges { node { tagName } } } \
+tags: refs(refPrefix: \"refs/tags/\", first: 1, orderBy: {field: TAG_COMMIT_DATE, direction: DESC}) { edges { node { name } } } \
+tree: object(expression: \"HEAD:\") { ... on Tree { entries { name type } } } \
+readme: object(expression: \"HEAD:README.md\") { ... on Blob { text } } \
+packageJson: object(expression: \"HEAD:package.json\") { ... on Blob { text } } \
+} \
+search(query: $search, type: ISSUE) { issueCount } \
+}";
 
 impl Github {
-    #[allow(dead_code)]
-    // create new instance with url
-    pub fn with_url(url: &str) -> Option<Github> {
-        let u = reqwest::Url::parse(url).ok()?;
-
-        // check if domain is "github.com"
-        if let Some(domain) = u.domain() {
-            if domain != "github.com" {
-                return None;
-            }
-        } else {
-            return None;
+    // create new instance with url, configured from the environment
+    pub fn with_url(url: &str) -> Result<Github> {
+        Github::with_config(url, GithubConfig::from_env())
+    }
+
+    // create new instance with url against the given endpoints
+    pub fn with_config(url: &str, config: GithubConfig) -> Result<Github> {
+        Github::with_transport(url, config, http::transport_from_env())
+    }
+
+    // create new instance with url, sending API requests through the given transport
+    pub fn with_transport(
+        url: &str,
+        config: GithubConfig,
+        transport: Arc<dyn Transport>,
+    ) -> Result<Github> {
+        let unsupported = || MetricError::Unsupported(format!("{} is not a GitHub repository", url));
+        let u = reqwest::Url::parse(url).map_err(|_| unsupported())?;
+
+        // check if host is one of the configured GitHub hosts
+        if !config.accepts(&u) {
+            return Err(unsupported());
         }
 
         // check if scheme is https or http
         let sch = u.scheme();
         if sch != "https" && sch != "http" {
-            return None;
+            return Err(unsupported());
         }
 
         // extract repo info from url
         let mut path = u.path().split('/').skip(1);
-        let link = url.to_string();
-        let owner = path.next()?.to_string();
-        let repo = path.next()?.to_string();
-
-        // http client
-        let mut headers = header::HeaderMap::new();
-        let t = format!("Bearer {}", std::env::var("GITHUB_TOKEN").ok()?);
-        headers.insert(
-            header::AUTHORIZATION,
-            header::HeaderValue::from_str(&t).ok()?,
-        );
-        headers.insert(
-            header::ACCEPT,
-            header::HeaderValue::from_static("application/vnd.github+json"),
-        );
-        headers.insert(
-            "X-GitHub-Api-Version",
-            header::HeaderValue::from_static("2022-11-28"),
-        );
-        let client = reqwest::blocking::Client::builder()
-            .user_agent("ECE461_Team19_CLI")
-            .default_headers(headers)
-            .build()
-            .ok()?;
+        let owner = path.next().filter(|s| !s.is_empty()).ok_or_else(unsupported)?.to_string();
+        let repo = path.next().filter(|s| !s.is_empty()).ok_or_else(unsupported)?.to_string();
+
+        // a token is required unless responses are served offline
+        if config.token.is_none() && !transport.is_offline() {
+            return Err(MetricError::Auth("GITHUB_TOKEN is not set".to_string()));
+        }
 
-        Some(Github {
+        Ok(Github {
             owner,
             repo,
-            link,
-            client,
+            label: url.to_string(),
+            config,
+            transport,
+            curves: Curves::default(),
+            facts: OnceLock::new(),
+            fetching: Mutex::new(()),
         })
     }
 
-    // GitHub REST API
-    // https://docs.github.com/en/rest?apiVersion=2022-11-28
-    pub fn rest_api(&self, path: &str) -> reqwest::Result<reqwest::blocking::Response> {
-        self.client
-            .get(format!(
-                "https://api.github.com/repos/{}/{}/{}",
-                self.owner, self.repo, path
-            ))
-            .send()
+    // report API usage under another package, e.g. the npm package the repository belongs to
+    pub fn labeled(mut self, label: &str) -> Github {
+        self.label = label.to_string();
+        self
     }
 
-    // REST API call with result in json format
-    pub fn rest_json(&self, path: &str) -> reqwest::Result<serde_json::Value> {
-        self.rest_api(path)?.json::<serde_json::Value>()
+    // score counts with the given curves instead of the defaults
+    pub fn with_curves(mut self, curves: Curves) -> Github {
+        self.curves = curves;
+        self
     }
 
-    // GitHub GraphQL API
-    pub fn graphql(&self, query: String) -> reqwest::Result<reqwest::blocking::Response> {
-        let github_token = std::env::var("GITHUB_TOKEN").unwrap();
-
-        self.client
-            .post("https://api.github.com/graphql")
-            .bearer_auth(github_token)
-            .body(query)
-            .send()
+    // send request with the GitHub API headers
+    fn send(&self, request: HttpRequest) -> http::Result<HttpResponse> {
+        let mut request = request
+            .label(&self.label)
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28");
+        if let Some(token) = &self.config.token {
+            request = request.header("Authorization", &format!("Bearer {}", token));
+        }
+        self.transport.send(&request)
     }
 
-    // GraphQL API call in json format
-    pub fn graph_json(&self, query: String) -> reqwest::Result<serde_json::Value> {
-        self.graphql(query)?.json::<serde_json::Value>()
+    // absolute url of a REST API path of the repository
+    fn rest_url(&self, path: &str) -> String {
+        format!(
+            "{}/repos/{}/{}/{}",
+            self.config.api_url, self.owner, self.repo, path
+        )
     }
 
-    // count how many pages the result has
-    // see: https://docs.github.com/en/rest/guides/using-pagination-in-the-rest-api?apiVersion=2022-11-28
-    pub fn rest_page_count(&self, path: &str) -> reqwest::Result<u32> {
-        let response = self.rest_api(path)?;
-        let header = response.headers().get("link");
-        if header.is_none() {
-            if !response.json::<serde_json::Value>()?.as_array().unwrap().is_empty()
-            {
-                return Ok(1);
-            } else {
-                return Ok(0);
-            }
-        }
-
-        // get substring with the page number
-        let res = header.unwrap().to_str().unwrap().split(',').nth(1).unwrap();
-        // get page number
-        let page = res.get(res.find("&page=").unwrap() + 6..res.find('>').unwrap());
+    // GitHub REST API
+    // https://docs.github.com/en/rest?apiVersion=2022-11-28
+    pub fn rest_api(&self, path: &str) -> http::Result<HttpResponse> {
+        self.send(HttpRequest::get(&self.rest_url(path)))
+    }
 
-        Ok(page.unwrap().parse::<u32>().unwrap())
+    // REST API call with result in json format
+    pub fn rest_json(&self, path: &str) -> http::Result<serde_json::Value> {
+        self.rest_api(path)?.json()
     }
 
-    pub fn get_name(&self) -> String {
-        self.owner.clone()
+    // GitHub GraphQL API
+    pub fn graphql(&self, query: String) -> http::Result<HttpResponse> {
+        self.send(HttpRequest::post(&self.config.graphql_url, query))
     }
 
-    pub fn get_version(&self) -> String{  
-        let json = self.graph_json(
-            format!(
-                "{{\"query\":\"query {{ repository(owner: \\\"{}\\\", name: \\\"{}\\\") {{ releases(last: 1) {{ edges {{ node {{ tagName }} }} }} }} }}\" }}",
-                self.owner, self.repo
-            )
-        ).unwrap();
-    
-    
-        let version = if let Some(tag_name) = json["data"]["repository"]["releases"]["edges"][0]["node"]["tagName"].as_str() {
-            tag_name.to_owned()
-        } else {
-            String::from("0.0.0")
-        };
-        
-        version
+    // GraphQL query with variables, deserialized into T
+    pub fn graphql_query<T: DeserializeOwned>(
+        &self,
+        query: &str,
+        variables: serde_json::Value,
+    ) -> http::Result<T> {
+        let response = self.graphql(graphql::body(query, variables)?)?;
+        graphql::decode(&self.config.graphql_url, response)
     }
-    
-}
-impl Metrics for Github {
-    fn ramp_up_time(&self) -> f64 {
-        // Specify the path of repo to clone into
-        let path_name = format!("cloned_{}_{}", self.owner, self.repo);
-        let repo_path = std::path::Path::new(&path_name);
-        if repo_path.is_dir()
-        {
-            std::fs::remove_dir_all(repo_path).unwrap();
+
+    // everything the metrics need about the repository, fetched once
+    pub fn facts(&self) -> http::Result<&RepositoryFacts> {
+        if let Some(facts) = self.facts.get() {
+            return Ok(facts);
+        }
+        let _fetching = self.fetching.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(facts) = self.facts.get() {
+            return Ok(facts);
         }
 
-        // Clone the repo
-        info!("cloning repository from {}", &self.link);
-        git2::Repository::clone(&self.link, repo_path).unwrap();
-        info!("repository cloned");
-
-        // Check if there is readme
-        let file = match std::fs::File::open(format!("{}/README.md", path_name)) {
-            Ok(file) => file,
-            Err(_) => {
-                std::fs::remove_dir_all(repo_path).unwrap();
-                return 0.0;
-            }
-        };
-        let reader = std::io::BufReader::new(file);
-
-        // Get the # of lines and calculate the score
-        info!("calculating ramp_up_score");
-        let lines = reader.lines().count();
-        let result = Self::calc_ramp_up_time(lines.try_into().unwrap_or(u32::MAX));
-        std::fs::remove_dir_all(repo_path).unwrap();
-        debug!("ramp_up_score: {:.2}", result);
-        info!("repository deleted");
-        result
-    }
-
-    fn correctness(&self) -> f64 {
-        // issues returns pull requests as well, so subtract pulls from issues
-        info!("calculating correctness_score");
-        let all = self.rest_page_count("issues?state=all&per_page=1").unwrap()
-            - self.rest_page_count("pulls?state=all&per_page=1").unwrap();
-        let closed = self
-            .rest_page_count("issues?state=closed&per_page=1")
-            .unwrap()
-            - self
-                .rest_page_count("pulls?state=closed&per_page=1")
-                .unwrap();
-        let result = Self::calc_correctness(all, closed);
-        debug!("correctness_score: {:.2}", result);
-        result
-    }
-
-    fn bus_factor(&self) -> f64 {
-        // call graphql api to get the data specified in the query
-        info!("calculating bus_factor_score");
-        let bus = self.graph_json(
-            format!("{{\"query\" : \"query {{ repository(owner:\\\"{}\\\", name:\\\"{}\\\") {{ mentionableUsers {{ totalCount }} }} }}\" }}", self.owner, self.repo)
-            ).unwrap();
-        let collaborators = bus["data"]["repository"]["mentionableUsers"]["totalCount"]
-            .as_i64()
-            .unwrap();
-        // calculate the score for bus factor
-        let score: f64 = ((2.0 * collaborators as f64) / (collaborators as f64 + 1.0)) - 1.0;
-        debug!("bus_factor_score: {:.2}", score);
-        score
-    }
-
-    fn responsiveness(&self) -> f64 {
-        // get pull requests last year with GraphQL API
+        // get pull requests last year
         // source of query:
         // https://stackoverflow.com/questions/61477294/how-to-filter-github-pull-request-by-updated-date-using-graphql
-        info!("calculating responsive_maintainer_score");
         let a_year_ago = (Utc::now() - chrono::naive::Days::new(365)).format("%Y-%m-%d");
-        let json = self.graph_json(
-            format!("{{\"query\" : \"query {{ search(query: \\\"repo:{}/{} is:pr updated:>={}\\\" type:ISSUE) {{ issueCount }} }}\" }}", self.owner, self.repo, a_year_ago)
-            ).unwrap();
-        let pulls = json["data"]["search"]["issueCount"].as_f64().unwrap();
-
-        let normal = Normal::new(0.0, 1.0).unwrap();
+        let search = format!("repo:{}/{} is:pr updated:>={}", self.owner, self.repo, a_year_ago);
+
+        info!("fetching repository facts");
+        let body = graphql::body(
+            REPOSITORY_FACTS_QUERY,
+            serde_json::json!({ "owner": self.owner, "name": self.repo, "search": search }),
+        )?;
+        // the date changes daily, cassettes and cached responses are keyed without it
+        let stable = graphql::body(
+            REPOSITORY_FACTS_QUERY,
+            serde_json::json!({ "owner": self.owner, "name": self.repo }),
+        )?;
+        let request = HttpRequest::post(&self.config.graphql_url, body).keyed_on(stable);
+        let facts: RepositoryFacts = graphql::decode(&self.config.graphql_url, self.send(request)?)?;
+        Ok(self.facts.get_or_init(|| facts))
+    }
 
-        let result = normal.cdf(pulls / 13.0 - 2.0);
-        debug!("responsive_maintainer_score: {:.2}", result);
-        result
+    // walk a REST collection of the repository page by page, following the `next` links
+    // see: https://docs.github.com/en/rest/guides/using-pagination-in-the-rest-api?apiVersion=2022-11-28
+    pub fn rest_pages(&self, path: &str) -> Pages<'_> {
+        Pages {
+            github: self,
+            next: Some(self.rest_url(path)),
+        }
     }
 
-    fn compatibility(&self) -> f64 {
-        // get license with github api
-        info!("calculating license_score");
-        let l = self.rest_json("license").unwrap();
-        let license = l["license"]["spdx_id"].as_str();
+    // count how many pages the result has
+    pub fn rest_page_count(&self, path: &str) -> http::Result<u32> {
+        let url = self.rest_url(path);
+        let response = self.send(HttpRequest::get(&url))?.error_for_status(&url)?;
+        let links = link::parse(response.header("link").unwrap_or(""));
+
+        // number of the last page, or one past the previous page when on the last one
+        let page = |rel: &str| {
+            link::find_rel(&links, rel)
+                .and_then(|l| l.query("page"))
+                .and_then(|p| p.parse::<u32>().ok())
+        };
+        if let Some(last) = page("last") {
+            return Ok(last);
+        }
+        if let Some(prev) = page("prev") {
+            return Ok(prev + 1);
+        }
 
-        // no license found
-        if license.is_none() {
-            return 0.0;
+        // no page numbers given, walk the remaining pages
+        if let Some(next) = link::find_rel(&links, "next") {
+            let mut count = 1;
+            for page in (Pages {
+                github: self,
+                next: Some(next.uri.clone()),
+            }) {
+                page?;
+                count += 1;
+            }
+            return Ok(count);
         }
 
-        let result = Self::calc_compatibility(license.unwrap());
-        debug!("license_score: {:.2}", result);
-        result
+        let empty = response.json()?.as_array().is_none_or(|a| a.is_empty());
+        Ok(if empty { 0 } else { 1 })
     }
 
-    fn reviewed_code(&self) -> f64 {
-        // gets the fraction of project code that was introduced through pull requests with a code review
-        info!("calculating reviewed_code_score");
+    // total number of items in a REST collection
+    pub fn rest_total_count(&self, path: &str) -> http::Result<u32> {
+        // with one item per page, the number of pages is the number of items
+        let separator = if path.contains('?') { '&' } else { '?' };
+        self.rest_page_count(&format!("{}{}per_page=1", path, separator))
+    }
+}
 
-        let json = self.graph_json(
-            format!(
-                "{{\"query\":\"query {{ repository(owner: \\\"{}\\\", name: \\\"{}\\\") {{ pullRequests(first: 100, orderBy: {{field: CREATED_AT, direction: DESC}}) {{ edges {{ node {{ number additions, number deletions }} }} }} }} }}\" }}",
-                self.owner, self.repo
-            )
-        ).unwrap();
+// pages of a REST collection, see Github::rest_pages
+pub struct Pages<'a> {
+    github: &'a Github,
+    next: Option<String>,
+}
 
-        let pulls = json["data"]["repository"]["pullRequests"]["edges"].as_array().unwrap();
-        //println!("numpulls = {}", pulls.len());
+impl Iterator for Pages<'_> {
+    type Item = http::Result<Vec<serde_json::Value>>;
 
-        let reviewsjson = self.graph_json(
-            format!(
-                "{{\"query\":\"query {{ repository(owner: \\\"{}\\\", name: \\\"{}\\\") {{ pullRequests(first: 100, orderBy: {{field: CREATED_AT, direction: DESC}}) {{ edges {{ node {{ number additions, number deletions, reviews(first: 1) {{ totalCount }} }} }} }} }} }}\" }}",
-                self.owner, self.repo
-            )
-        ).unwrap();
+    fn next(&mut self) -> Option<Self::Item> {
+        let url = self.next.take()?;
+        let response = match self
+            .github
+            .send(HttpRequest::get(&url))
+            .and_then(|r| r.error_for_status(&url))
+        {
+            Ok(response) => response,
+            Err(e) => return Some(Err(e)),
+        };
 
-        let reviewed_pulls = reviewsjson["data"]["repository"]["pullRequests"]["edges"].as_array().unwrap();
+        let links = link::parse(response.header("link").unwrap_or(""));
+        self.next = link::find_rel(&links, "next").map(|l| l.uri.clone());
+        Some(response.json().map(|json| match json {
+            serde_json::Value::Array(items) => items,
+            _ => Vec::new(),
+        }))
+    }
+}
 
-        let mut reviewed_pulls_count = 0;
+impl Collector for Github {
+    fn collect(&self) -> Result<RepositoryFacts> {
+        Ok(self.facts()?.clone())
+    }
+}
 
-        for pull in reviewed_pulls {
-            //println!("pull = {}", pull);
-            let reviews = pull["node"]["reviews"]["totalCount"].as_i64();
-            if reviews.unwrap_or(0) > 0 {
-                reviewed_pulls_count += 1;
-            }
-        }
-        //println!("reviewed code score = {}", reviewed_code_score);
-        reviewed_pulls_count as f64 / pulls.len() as f64
+// formulas are in scoring.rs, the facts are fetched once for all metrics
+impl Metrics for Github {
+    fn ramp_up_time(&self) -> Result<Evaluation> {
+        Ok(scoring::ramp_up(self.facts()?, &self.curves))
     }
 
+    fn correctness(&self) -> Result<Evaluation> {
+        Ok(scoring::correctness(self.facts()?))
+    }
 
-    fn pinning_practice(&self) -> f64 {
-        // use github api to get dependency count
-        info!("calculating pinning_practice_score");
+    fn bus_factor(&self) -> Result<Evaluation> {
+        Ok(scoring::bus_factor(self.facts()?, &self.curves))
+    }
 
-        let response = self.rest_json("contents").unwrap(); 
-        let response_str = serde_json::to_string(&response).unwrap();
-        let contents: Vec<GithubPinningPractice> = serde_json::from_str(&response_str).unwrap();
+    fn responsiveness(&self) -> Result<Evaluation> {
+        Ok(scoring::responsiveness(self.facts()?, &self.curves))
+    }
 
-        let mut package_url = String::new();
-        for content in contents {
-            if content.name == "package.json" {
-                package_url = content.url; 
-            } 
-        }
-        
-        // In case of no package.json file -> make 0 to not effect score
-        let mut pinning_practice_score = 0.0 as f64;
-        if true { 
-            pinning_practice_score = 0.0;
-        } else {
-            let client = reqwest::blocking::Client::builder()
-            .user_agent("ECE461Project")
-            .build();
-        let response = client.unwrap().get(package_url).send();
-
-        let mut num_dependencies = 0.0;
-        if let Ok(response) = response {
-            let body_string = response.text().unwrap();
-            let body_json_string: PinningPracticePackageJSON = serde_json::from_str(&body_string).unwrap();
-            let content_string = body_json_string.content.unwrap();
-            let trimmed_content_string = content_string.trim_matches('\n').to_string();
-            
-            pyo3::prepare_freethreaded_python();
-            Python::with_gil(|py| {
-                let base64_module = py.import("base64").unwrap();
-                let base64_decode_fn = base64_module.getattr("b64decode").unwrap();
-
-                let decoded_content_bytes = base64_decode_fn.call1((trimmed_content_string,)).unwrap().extract::<Vec<u8>>().unwrap();
-                let decoded_content_string = String::from_utf8(decoded_content_bytes).unwrap();
-                
-                // error fixing -- edit string to devDependencies
-                let mut edited_decoded_content_string = String::new();
-                edited_decoded_content_string.push('{');
-                let mut word_check = 0;
-                for word in decoded_content_string.split_whitespace() {
-                    if word == "\"devDependencies\":" {
-                        word_check = 1;
-                    }
-                    if word_check == 1 {
-                        for c in word.chars() {
-                            edited_decoded_content_string.push(c);
-                        }
-                        edited_decoded_content_string.push(' ');
-                    }
-                }
-                if edited_decoded_content_string.is_empty() {
-                    num_dependencies = 0.0;
-                } else {
-                    let dict_py_json: serde_json::Value = serde_json::from_str(&edited_decoded_content_string).unwrap();
-                    let dev_dependencies = dict_py_json["devDependencies"].as_object().unwrap();
-                    let dev_dependencies_vals = dev_dependencies.values().cloned().collect::<Vec<_>>();
-                    num_dependencies = dev_dependencies_vals.len() as f64;
-                }
-            }); 
-        } else {
-            num_dependencies = 0.0;
-        }
-        pinning_practice_score = if num_dependencies == 0.0 {1.0} else {1.0 / num_dependencies}; 
-        }
+    fn compatibility(&self) -> Result<Evaluation> {
+        Ok(scoring::compatibility(self.facts()?))
+    }
 
-        pinning_practice_score
+    fn reviewed_code(&self) -> Result<Evaluation> {
+        Ok(scoring::reviewed_code(self.facts()?))
     }
 
+    fn pinning_practice(&self) -> Result<Evaluation> {
+        Ok(scoring::pinning_practice(self.facts()?))
+    }
 }
 
-/*#[allow(dead_code)]
-pub fn get_name(url: &String) -> String{
-    let git = match Github::with_url(url) {
-        Some(git) => git,
-        None => {
-            println!("Error while processing url: {}", url);
-            return String::from("None");
-        }
-    };
-    return git.owner;
-}*/
-
-/*#[allow(dead_code)]
-pub fn get_version(url: &String) -> String{
-    let git = match Github::with_url(url) {
-        Some(git) => git,
-        None => {
-            println!("Error while processing url: {}", url);
-            return String::from("0.0.0");
-        }
-    };
-
-    let json = git.graph_json(
-        format!(
-            "{{\"query\":\"query {{ repository(owner: \\\"{}\\\", name: \\\"{}\\\") {{ releases(last: 1) {{ edges {{ node {{ tagName }} }} }} }} }}\" }}",
-            git.owner, git.repo
-        )
-    ).unwrap();
-
-
-    let version = if let Some(tag_name) = json["data"]["repository"]["releases"]["edges"][0]["node"]["tagName"].as_str() {
-        tag_name.to_owned()
-    } else {
-        String::from("0.0.0")
-    };
-    println!("Version: {}", version);
-    
-    return git.owner;
-} */
+    #[cfg(test)]
+    // client for a repository with `count` pull requests updated in the last year
+    fn with_pulls_last_year(count: u32) -> Github {
+        let mut body: serde_json::Value =
+            serde_json::from_str(&facts_response(serde_json::json!({}))).unwrap();
+        body["data"]["search"]["issueCount"] = serde_json::json!(count);
+        offline_github(&body.to_string())
+    }
 
     // testing ramp_up_time
     #[test]
     fn ramp_up_time_no_readme() {
-        let g = Github::with_url("https://github.com/phil-opp/llvm-tools").unwrap();
-        assert_eq!(0.0, g.ramp_up_time());
+        let g = offline_github(&facts_response(serde_json::json!({ "readme": null })));
+        assert_eq!(0.0, g.ramp_up_time().unwrap().value().unwrap());
     }
 
     #[test]
     fn ramp_up_time_normal_case() {
-        let g = Github::with_url("https://github.com/yt-dlp/yt-dlp").unwrap();
-        assert!(g.ramp_up_time() > 0.0);
+        let readme = "line\n".repeat(40);
+        let g = offline_github(&facts_response(serde_json::json!({ "readme": { "text": readme } })));
+        assert_eq!(
+            Some(Curves::default().ramp_up.apply(40.0)),
+            g.ramp_up_time().unwrap().value()
+        );
+        assert!(g.ramp_up_time().unwrap().value().unwrap() > 0.0);
     }
 
     #[test]
     fn ramp_up_time_max() {
         // 147 lines
-        let g = Github::with_url("https://github.com/graphql/graphql-js").unwrap();
-        assert!(g.ramp_up_time() >= 0.99);
+        let readme = "line\n".repeat(147);
+        let g = offline_github(&facts_response(serde_json::json!({ "readme": { "text": readme } })));
+        assert!(g.ramp_up_time().unwrap().value().unwrap() >= 0.99);
     }
 
     // testing correctness
     #[test]
     fn correctness_no_issues() {
-        let g = Github::with_url("https://github.com/thinkloop/map-or-similar").unwrap();
-        assert!(g.correctness() == 0.0);
+        let g = offline_github(&facts_response(serde_json::json!({})));
+        assert!(g.correctness().unwrap().value().unwrap() == 0.0);
     }
 
-
     #[test]
     fn correctness_max() {
         // 0 open, 1 closed issues
-        let g = Github::with_url("https://github.com/crypto-browserify/md5.js").unwrap();
-        assert!(g.correctness() == 1.0);
+        let g = offline_github(&facts_response(serde_json::json!({
+            "issues": { "totalCount": 1 },
+            "closedIssues": { "totalCount": 1 }
+        })));
+        assert!(g.correctness().unwrap().value().unwrap() == 1.0);
     }
 
     #[test]
     fn correctness_normal_case() {
-        let g = Github::with_url("https://github.com/neovim/neovim").unwrap();
-        assert!(g.correctness() >= 0.0);
+        // 1 open, 4 closed issues
+        let g = offline_github(&facts_response(serde_json::json!({
+            "issues": { "totalCount": 5 },
+            "closedIssues": { "totalCount": 4 }
+        })));
+        assert_eq!(Some(0.8), g.correctness().unwrap().value());
     }
 
     // testing bus factor
     #[test]
     fn bus_factor_0_contributors() {
-        let g = Github::with_url("https://github.com/sergi/ftp-response-parser").unwrap();
-        assert!(g.bus_factor() <= 0.05);
+        let g = offline_github(&facts_response(serde_json::json!({
+            "mentionableUsers": { "totalCount": 0 }
+        })));
+        assert!(g.bus_factor().unwrap().value().unwrap() <= 0.05);
     }
 
     #[test]
     fn bus_factor_normal_case() {
-        let g = Github::with_url("https://github.com/EverestAPI/Olympus").unwrap();
-        assert!(g.bus_factor() > 0.5);
+        // 12 collaborators
+        let g = offline_github(&facts_response(serde_json::json!({
+            "mentionableUsers": { "totalCount": 12 }
+        })));
+        assert!(g.bus_factor().unwrap().value().unwrap() > 0.5);
     }
 
     // testing responsiveness
     #[test]
     fn responsiveness_0() {
-        let g = Github::with_url("https://github.com/adafruit/Adafruit-MPU6050-PCB").unwrap();
-        assert!(g.responsiveness() < 0.05);
+        let g = with_pulls_last_year(0);
+        assert!(g.responsiveness().unwrap().value().unwrap() < 0.05);
     }
 
     #[test]
     fn responsiveness_normal_case() {
-        let g = Github::with_url("https://github.com/ImageMagick/ImageMagick").unwrap();
-        assert!(g.responsiveness() > 0.0);
+        let g = with_pulls_last_year(40);
+        assert!(g.responsiveness().unwrap().value().unwrap() > 0.0);
     }
 
     // testing compatibility
     #[test]
     fn compatibility_no_license() {
-        let g = Github::with_url("https://github.com/cloudinary/cloudinary_npm").unwrap();
-        assert!(g.compatibility() == 0.0);
+        let g = offline_github(&facts_response(serde_json::json!({ "licenseInfo": null })));
+        assert!(g.compatibility().unwrap().value().is_none());
     }
 
     #[test]
     fn compatibility_lgpl_3() {
-        let g = Github::with_url("https://github.com/haskell/ghcup-hs").unwrap();
-        assert!(g.compatibility() == 1.0);
+        let g = offline_github(&facts_response(serde_json::json!({
+            "licenseInfo": { "spdxId": "LGPL-3.0" }
+        })));
+        assert!(g.compatibility().unwrap().value().unwrap() == 1.0);
     }
 
     #[test]
     fn compatibility_mit() {
-        let g = Github::with_url("https://github.com/microsoft/vscode").unwrap();
-        assert!(g.compatibility() == 1.0);
+        let g = offline_github(&facts_response(serde_json::json!({
+            "licenseInfo": { "spdxId": "MIT" }
+        })));
+        assert!(g.compatibility().unwrap().value().unwrap() == 1.0);
     }
 
     #[test]
     fn compatibility_apache() {
-        let g = Github::with_url("https://github.com/haskell/haskell-language-server").unwrap();
-        assert!(g.compatibility() == 0.0);
+        let g = offline_github(&facts_response(serde_json::json!({
+            "licenseInfo": { "spdxId": "Apache-2.0" }
+        })));
+        assert!(g.compatibility().unwrap().value().unwrap() == 0.0);
     }
 
     //testing reviewed code metric
     #[test]
     fn test_reveiwed_code() {
-        let g = Github::with_url("https://github.com/PurdueSoftEng/CLI-Tool").unwrap();
-        assert!(g.reviewed_code() <= 0.5);
+        // 2 of the last 10 pull requests reviewed
+        let pulls: Vec<_> = (0..10)
+            .map(|i| serde_json::json!({ "node": { "reviews": { "totalCount": if i < 2 { 1 } else { 0 } } } }))
+            .collect();
+        let g = offline_github(&facts_response(serde_json::json!({
+            "pullRequests": { "edges": pulls }
+        })));
+        assert_eq!(Some(0.2), g.reviewed_code().unwrap().value());
     }
 
    // testing pinningPractice metric 
+   #[cfg(test)]
+   // client for a repository with the given package.json
+   fn with_package_json(package_json: &str) -> Github {
+       offline_github(&facts_response(serde_json::json!({
+           "tree": { "entries": [{ "name": "package.json", "type": "blob" }] },
+           "packageJson": { "text": package_json }
+       })))
+   }
+
+   #[test]
+   fn pinning_no_package_json() {
+       let g = offline_github(&facts_response(serde_json::json!({
+           "tree": { "entries": [{ "name": "Cargo.toml", "type": "blob" }] }
+       })));
+       assert!(g.pinning_practice().unwrap().value().is_none());
+   }
+
    #[test]
    fn pinning_zero() {
-       let g = Github::with_url("https://github.com/PurdueSoftEng/CLI-Tool").unwrap();
-       assert_eq!(0.0, g.pinning_practice());
+       let g = with_package_json(
+           r#"{ "dependencies": { "a": "^1.0.0", "b": "*" }, "devDependencies": { "c": "latest" } }"#,
+       );
+       assert_eq!(Some(0.0), g.pinning_practice().unwrap().value());
    }
 
    #[test]
    fn pinning_zero_point_one() {
-       let g = Github::with_url("https://github.com/brix/crypto-js").unwrap();
-       assert_eq!(0.0, g.pinning_practice());
+       // 1 of 10 pinned
+       let g = with_package_json(
+           r#"{
+               "dependencies": { "a": "~1.2.3", "b": "^1.0.0", "c": "^2.0.0", "d": ">=1" },
+               "devDependencies": {
+                   "e": "^3.1.0", "f": "*", "g": "latest", "h": "1.x", "i": "^4.0.0", "j": "~1"
+               }
+           }"#,
+       );
+       assert_eq!(Some(0.1), g.pinning_practice().unwrap().value());
    }
 
    #[test]
    fn pinning_one_half() {
-       let g = Github::with_url("https://github.com/stefanbuck/peer-version-check").unwrap();
-       assert_eq!(0.0, g.pinning_practice());
+       // 2 of 4 pinned
+       let g = with_package_json(
+           r#"{
+               "dependencies": { "a": "1.2.3", "b": "^1.2.3" },
+               "devDependencies": { "c": "~0.4.1", "d": "latest" }
+           }"#,
+       );
+       assert_eq!(Some(0.5), g.pinning_practice().unwrap().value());
+   }
+
+   // testing endpoint configuration
+   #[test]
+   fn config_github_com() {
+       let c = GithubConfig::for_host("github.com");
+       assert_eq!("https://api.github.com", c.api_url);
+       assert_eq!("https://api.github.com/graphql", c.graphql_url);
+   }
+
+   #[test]
+   fn config_enterprise() {
+       let c = GithubConfig::for_host("github.example.com");
+       assert_eq!("https://github.example.com/api/v3", c.api_url);
+       assert_eq!("https://github.example.com/api/graphql", c.graphql_url);
+   }
+
+   #[test]
+   fn with_config_enterprise_url() {
+       let mut c = GithubConfig::for_host("github.example.com");
+       c.token = Some("token".to_string());
+       assert!(Github::with_config("https://github.example.com/owner/repo", c.clone()).is_ok());
+       assert!(Github::with_config("https://github.com/owner/repo", c.clone()).is_err());
+       assert!(Github::with_config("https://github.example.com/owner", c).is_err());
+   }
+
+   #[test]
+   fn with_config_local_mock() {
+       let mut c = GithubConfig::for_host("localhost:8080");
+       c.api_url = "http://localhost:8080".to_string();
+       c.token = Some("token".to_string());
+       assert!(Github::with_config("http://localhost:8080/owner/repo", c.clone()).is_ok());
+       assert!(Github::with_config("http://localhost:9090/owner/repo", c).is_err());
+   }
+
+   #[test]
+   fn with_config_no_token() {
+       let c = GithubConfig::for_host("github.com");
+       assert!(matches!(
+           Github::with_config("https://github.com/owner/repo", c),
+           Err(MetricError::Auth(_))
+       ));
+   }
+
+   // testing against recorded responses
+   #[cfg(test)]
+   // GraphQL response with repository facts, fields of the repository replaced by `repository`
+   pub fn facts_response(repository: serde_json::Value) -> String {
+       let mut facts = serde_json::json!({
+           "repository": {
+               "name": "repo",
+               "issues": { "totalCount": 0 },
+               "closedIssues": { "totalCount": 0 },
+               "pullRequests": { "edges": [] },
+               "licenseInfo": null,
+               "mentionableUsers": { "totalCount": 0 },
+               "releases": { "edges": [] },
+               "tags": { "edges": [] },
+               "tree": { "entries": [] },
+               "readme": null,
+               "packageJson": null
+           },
+           "search": { "issueCount": 0 }
+       });
+       for (key, value) in repository.as_object().unwrap() {
+           facts["repository"][key] = value.clone();
+       }
+       serde_json::json!({ "data": facts }).to_string()
+   }
+
+   #[cfg(test)]
+   // facts of owner/repo, fields of the repository replaced by `repository`
+   pub fn offline_facts(repository: serde_json::Value) -> RepositoryFacts {
+       let response: serde_json::Value =
+           serde_json::from_str(&facts_response(repository)).unwrap();
+       serde_json::from_value(response["data"].clone()).unwrap()
+   }
+
+   #[cfg(test)]
+   // client for owner/repo answering GraphQL queries with `body`
+   pub fn offline_github(body: &str) -> Github {
+       let stub = crate::http::tests::StubTransport::default().with(
+           "https://api.github.com/graphql",
+           200,
+           body,
+       );
+       Github::with_transport(
+           "https://github.com/owner/repo",
+           GithubConfig::for_host("github.com"),
+           Arc::new(stub),
+       )
+       .unwrap()
+   }
+
+   #[test]
+   fn ramp_up_time_offline() {
+       let readme = "line\n".repeat(150);
+       let g = offline_github(&facts_response(serde_json::json!({ "readme": { "text": readme } })));
+       assert!(g.ramp_up_time().unwrap().value().unwrap() >= 0.99);
+
+       let g = offline_github(&facts_response(serde_json::json!({})));
+       assert_eq!(0.0, g.ramp_up_time().unwrap().value().unwrap());
+   }
+
+   #[test]
+   fn compatibility_offline() {
+       let g = offline_github(&facts_response(
+           serde_json::json!({ "licenseInfo": { "spdxId": "MIT" } }),
+       ));
+       assert!(g.compatibility().unwrap().value().unwrap() == 1.0);
+
+       let g = offline_github(&facts_response(serde_json::json!({})));
+       assert_eq!(
+           Score::Unknown("no license found".to_string()),
+           g.compatibility().unwrap().score
+       );
+   }
+
+   #[test]
+   fn correctness_offline() {
+       let g = offline_github(&facts_response(serde_json::json!({
+           "issues": { "totalCount": 4 },
+           "closedIssues": { "totalCount": 3 }
+       })));
+       let e = g.correctness().unwrap();
+       assert!(e.value().unwrap() == 0.75);
+       assert_eq!(4, e.evidence["issues"]);
+       assert_eq!(3, e.evidence["closed_issues"]);
+       assert_eq!("3 of 4 issues are closed", e.explanation);
+   }
+
+   #[test]
+   fn bus_factor_offline() {
+       let g = offline_github(&facts_response(
+           serde_json::json!({ "mentionableUsers": { "totalCount": 3 } }),
+       ));
+       assert_eq!(0.5, g.bus_factor().unwrap().value().unwrap());
+   }
+
+   #[test]
+   fn reviewed_code_offline() {
+       let reviews = |n: u32| serde_json::json!({ "node": { "reviews": { "totalCount": n } } });
+       let g = offline_github(&facts_response(serde_json::json!({
+           "pullRequests": { "edges": [reviews(2), reviews(0), reviews(0), reviews(0)] }
+       })));
+       assert_eq!(0.25, g.reviewed_code().unwrap().value().unwrap());
+
+       // no pull requests is not the same as no reviews
+       let g = offline_github(&facts_response(serde_json::json!({})));
+       assert!(g.reviewed_code().unwrap().value().is_none());
+   }
+
+   #[test]
+   fn responsiveness_evidence_offline() {
+       let g = offline_github(&facts_response(serde_json::json!({})).replace(
+           "\"issueCount\":0",
+           "\"issueCount\":8",
+       ));
+       let e = g.responsiveness().unwrap();
+       assert_eq!(8, e.evidence["pull_requests_last_year"]);
+       assert_eq!(Some(&13.0), e.parameters.get("scale"));
+   }
+
+   #[test]
+   fn custom_curve_offline() {
+       let g = offline_github(&facts_response(
+           serde_json::json!({ "mentionableUsers": { "totalCount": 5 } }),
+       ))
+       .with_curves(Curves {
+           bus_factor: crate::metrics::curve::Curve::Logistic {
+               midpoint: 5.0,
+               steepness: 1.0,
+           },
+           ..Curves::default()
+       });
+       let e = g.bus_factor().unwrap();
+       assert_eq!(Some(0.5), e.value());
+       assert_eq!(Some(&5.0), e.parameters.get("midpoint"));
+   }
+
+   #[test]
+   fn pinning_practice_offline() {
+       let g = offline_github(&facts_response(serde_json::json!({})));
+       assert_eq!(
+           Score::Unknown("no package.json".to_string()),
+           g.pinning_practice().unwrap().score
+       );
+
+       let g = offline_github(&facts_response(serde_json::json!({
+           "tree": { "entries": [{ "name": "package.json", "type": "blob" }] },
+           "packageJson": { "text": "{\"dependencies\":{\"left-pad\":\"^1.3.0\"}}" }
+       })));
+       let e = g.pinning_practice().unwrap();
+       assert_eq!(Some(0.0), e.value());
+       assert_eq!("^1.3.0", e.evidence["dependencies"]["left-pad"]);
+   }
+
+   #[test]
+   fn identity_offline() {
+       let g = offline_github(&facts_response(serde_json::json!({
+           "releases": { "edges": [{ "node": { "tagName": "v1.2.3" } }] }
+       })));
+       let id = g.identity().unwrap();
+       assert_eq!("repo", id.name);
+       assert_eq!(Some("1.2.3".to_string()), id.version);
+
+       let g = offline_github(&facts_response(serde_json::json!({})));
+       assert_eq!(None, g.identity().unwrap().version);
+   }
+
+   #[test]
+   fn graphql_errors_surfaced() {
+       let g = offline_github(
+           "{\"data\":{\"repository\":null,\"search\":{\"issueCount\":0}},\"errors\":[{\"type\":\"NOT_FOUND\",\"message\":\"Could not resolve to a Repository with the name 'owner/repo'.\"}]}",
+       );
+       assert!(matches!(g.facts(), Err(http::HttpError::Graphql(_))));
+       assert!(matches!(g.correctness(), Err(MetricError::NotFound(_))));
+   }
+
+   #[test]
+   fn bad_credentials_reported() {
+       let stub = crate::http::tests::StubTransport::default().with(
+           "https://api.github.com/graphql",
+           401,
+           "{\"message\":\"Bad credentials\"}",
+       );
+       let g = Github::with_transport(
+           "https://github.com/owner/repo",
+           GithubConfig::for_host("github.com"),
+           Arc::new(stub),
+       )
+       .unwrap();
+       assert!(matches!(g.bus_factor(), Err(MetricError::Auth(_))));
+   }
+
+   // testing pagination
+   #[test]
+   fn page_count_from_last_link() {
+       let stub = crate::http::tests::StubTransport::default().with_headers(
+           "https://api.github.com/repos/owner/repo/issues?state=all&per_page=1",
+           200,
+           "[{}]",
+           &[(
+               "link",
+               "<https://api.github.com/repositories/1/issues?page=57&state=all&per_page=1>; rel=\"last\", \
+                <https://api.github.com/repositories/1/issues?page=2&state=all&per_page=1>; rel=\"next\"",
+           )],
+       );
+       let g = Github::with_transport(
+           "https://github.com/owner/repo",
+           GithubConfig::for_host("github.com"),
+           Arc::new(stub),
+       )
+       .unwrap();
+       assert_eq!(57, g.rest_total_count("issues?state=all").unwrap());
+   }
+
+   #[test]
+   fn pages_follow_next_links() {
+       let stub = crate::http::tests::StubTransport::default()
+           .with_headers(
+               "https://api.github.com/repos/owner/repo/contributors?per_page=2",
+               200,
+               "[{\"login\":\"a\"},{\"login\":\"b\"}]",
+               &[("link", "<https://api.github.com/repositories/1/contributors?per_page=2&page=2>; rel=\"next\"")],
+           )
+           .with_headers(
+               "https://api.github.com/repositories/1/contributors?per_page=2&page=2",
+               200,
+               "[{\"login\":\"c\"}]",
+               &[("link", "<https://api.github.com/repositories/1/contributors?per_page=2&page=1>; rel=\"prev\"")],
+           );
+       let g = Github::with_transport(
+           "https://github.com/owner/repo",
+           GithubConfig::for_host("github.com"),
+           Arc::new(stub),
+       )
+       .unwrap();
+       let pages: Vec<_> = g
+           .rest_pages("contributors?per_page=2")
+           .map(|p| p.unwrap().len())
+           .collect();
+       assert_eq!(vec![2, 1], pages);
+       assert_eq!(
+           2,
+           g.rest_page_count("contributors?per_page=2").unwrap()
+       );
+   }
+
+   #[test]
+   fn pages_stop_on_error() {
+       let stub = crate::http::tests::StubTransport::default().with(
+           "https://api.github.com/repos/owner/repo/commits",
+           404,
+           "{\"message\":\"Not Found\"}",
+       );
+       let g = Github::with_transport(
+           "https://github.com/owner/repo",
+           GithubConfig::for_host("github.com"),
+           Arc::new(stub),
+       )
+       .unwrap();
+       let mut pages = g.rest_pages("commits");
+       assert!(matches!(
+           pages.next(),
+           Some(Err(http::HttpError::Status { status: 404, .. }))
+       ));
+       assert!(pages.next().is_none());
+   }
+
+   #[test]
+   fn replay_facts_on_later_days() {
+       // the cassette was saved with the search date of the day it was written
+       let g = Github::with_transport(
+           "https://github.com/owner/repo",
+           GithubConfig::for_host("github.com"),
+           Arc::new(crate::http::tests::cassettes()),
+       )
+       .unwrap();
+       assert_eq!(Some(0.75), g.correctness().unwrap().value());
+       assert_eq!(Some(1.0), g.compatibility().unwrap().value());
    }
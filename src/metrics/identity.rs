@@ -0,0 +1,202 @@
+// name and version of a package
+//
+// npm packages are identified by their registry document. GitHub repositories by the
+// package.json of the default branch, falling back to the repository name and the
+// latest release or tag.
+use crate::metrics::facts::RepositoryFacts;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PackageIdentity {
+    pub name: String,
+    // None if nothing was published, released or tagged
+    pub version: Option<String>,
+    // where the version was read from, Repository if there is none
+    pub source: Source,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    // npm registry document
+    Registry,
+    PackageJson,
+    Release,
+    Tag,
+    Repository,
+}
+
+impl Source {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Source::Registry => "registry",
+            Source::PackageJson => "package_json",
+            Source::Release => "release",
+            Source::Tag => "tag",
+            Source::Repository => "repository",
+        }
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// name and latest version of an npm registry document, None without a name
+pub fn from_registry(document: &Value) -> Option<PackageIdentity> {
+    let name = text(document, "name")?;
+    let version = document
+        .get("dist-tags")
+        .and_then(|tags| text(tags, "latest"))
+        .or_else(|| text(document, "version"));
+    Some(PackageIdentity {
+        name,
+        version,
+        source: Source::Registry,
+    })
+}
+
+// identity of a repository from its facts
+pub fn from_facts(facts: &RepositoryFacts) -> PackageIdentity {
+    let repository = &facts.repository;
+    let manifest: Option<Value> = repository
+        .package_json
+        .as_ref()
+        .and_then(|blob| blob.text.as_deref())
+        .and_then(|text| serde_json::from_str(text).ok());
+
+    let name = manifest
+        .as_ref()
+        .and_then(|m| text(m, "name"))
+        .unwrap_or_else(|| repository.name.clone());
+
+    // semantic-release keeps a placeholder in package.json and publishes tags
+    let version = manifest
+        .as_ref()
+        .and_then(|m| text(m, "version"))
+        .filter(|v| !v.starts_with("0.0.0-"))
+        .map(|v| (v, Source::PackageJson))
+        .or_else(|| {
+            let release = repository.releases.edges.first()?;
+            Some((tag_version(&release.node.tag_name), Source::Release))
+        })
+        .or_else(|| {
+            let tag = repository.tags.edges.first()?;
+            Some((tag_version(&tag.node.name), Source::Tag))
+        });
+
+    match version {
+        Some((version, source)) => PackageIdentity {
+            name,
+            version: Some(version),
+            source,
+        },
+        None => PackageIdentity {
+            name,
+            version: None,
+            source: Source::Repository,
+        },
+    }
+}
+
+// version of a tag such as v1.2.3, release-1.2.3 or pkg@1.2.3, the tag itself otherwise
+pub fn tag_version(tag: &str) -> String {
+    let Some(start) = tag.find(|c: char| c.is_ascii_digit()) else {
+        return tag.to_string();
+    };
+    let prefix = &tag[..start];
+    if prefix.is_empty()
+        || prefix.eq_ignore_ascii_case("v")
+        || prefix.ends_with(['-', '_', '@', '/'])
+    {
+        tag[start..].to_string()
+    } else {
+        tag.to_string()
+    }
+}
+
+// non-empty string field of a JSON object
+fn text(value: &Value, key: &str) -> Option<String> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::facts::Collector;
+    use crate::metrics::github::{facts_response, offline_github};
+
+    fn identity(repository: Value) -> PackageIdentity {
+        from_facts(
+            &offline_github(&facts_response(repository))
+                .collect()
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn package_json_first() {
+        let id = identity(serde_json::json!({
+            "packageJson": { "text": "{\"name\":\"@scope/pkg\",\"version\":\"2.0.1\"}" },
+            "releases": { "edges": [{ "node": { "tagName": "v1.2.3" } }] }
+        }));
+        assert_eq!("@scope/pkg", id.name);
+        assert_eq!(Some("2.0.1".to_string()), id.version);
+        assert_eq!(Source::PackageJson, id.source);
+    }
+
+    #[test]
+    fn releases_and_tags() {
+        let id = identity(serde_json::json!({
+            "packageJson": { "text": "{\"name\":\"pkg\",\"version\":\"0.0.0-development\"}" },
+            "releases": { "edges": [{ "node": { "tagName": "v1.2.3" } }] },
+            "tags": { "edges": [{ "node": { "name": "v1.3.0-beta" } }] }
+        }));
+        assert_eq!("pkg", id.name);
+        assert_eq!(Some("1.2.3".to_string()), id.version);
+        assert_eq!(Source::Release, id.source);
+
+        let id = identity(serde_json::json!({
+            "tags": { "edges": [{ "node": { "name": "release-4.1" } }] }
+        }));
+        assert_eq!("repo", id.name);
+        assert_eq!(Some("4.1".to_string()), id.version);
+        assert_eq!(Source::Tag, id.source);
+
+        let id = identity(serde_json::json!({ "packageJson": { "text": "not json" } }));
+        assert_eq!("repo", id.name);
+        assert_eq!(None, id.version);
+        assert_eq!(Source::Repository, id.source);
+    }
+
+    #[test]
+    fn tag_versions() {
+        assert_eq!("1.2.3", tag_version("v1.2.3"));
+        assert_eq!("1.2.3", tag_version("pkg@1.2.3"));
+        assert_eq!("2.0", tag_version("2.0"));
+        assert_eq!("nightly", tag_version("nightly"));
+        assert_eq!("es2015", tag_version("es2015"));
+    }
+
+    #[test]
+    fn registry_document() {
+        let id = from_registry(&serde_json::json!({
+            "name": "left-pad",
+            "dist-tags": { "latest": "1.3.0", "next": "2.0.0-rc" }
+        }))
+        .unwrap();
+        assert_eq!("left-pad", id.name);
+        assert_eq!(Some("1.3.0".to_string()), id.version);
+        assert_eq!(Source::Registry, id.source);
+        assert_eq!(None, from_registry(&serde_json::json!({ "versions": {} })));
+    }
+}
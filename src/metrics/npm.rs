@@ -1,27 +1,49 @@
-use crate::metrics::github::Github;
-use crate::metrics::Metrics;
+use crate::http::{self, HttpRequest, Transport};
+use crate::metrics::curve::Curves;
+use crate::metrics::facts::{Collector, RepositoryFacts};
+use crate::metrics::github::{Github, GithubConfig};
+use crate::metrics::identity::{self, PackageIdentity};
+use crate::metrics::{Evaluation, MetricError, Metrics, Result};
 use serde_json::Value; // for parsing json
-use pyo3::prelude::*;
+use std::sync::Arc;
 
 pub struct Npm {
-    gh: Box<dyn Metrics>,
+    gh: Github,
+    // name and latest version published to the registry
+    registry: Option<PackageIdentity>,
 }
 
 impl Npm {
     #[allow(dead_code)]
-    pub fn with_url(url: &str) -> Option<Npm> {
+    pub fn with_url(url: &str) -> Result<Npm> {
+        Npm::with_transport(url, GithubConfig::from_env(), http::transport_from_env())
+    }
+
+    // create new instance, fetching the registry document and GitHub data through the given transport
+    pub fn with_transport(
+        url: &str,
+        config: GithubConfig,
+        transport: Arc<dyn Transport>,
+    ) -> Result<Npm> {
+        if !url.starts_with("https://www.npmjs.com/package/") {
+            return Err(MetricError::Unsupported(format!("{} is not an npm package", url)));
+        }
         let npm_url = url.replace(
             "https://www.npmjs.com/package/",
             "https://registry.npmjs.org/",
         );
 
-        let npm_url = reqwest::blocking::get(npm_url).ok()?.text().ok()?;
+        let npm_url = transport
+            .send(&HttpRequest::get(&npm_url).label(url))?
+            .error_for_status(&npm_url)?
+            .body;
 
         // input url
         let input: &str = &npm_url;
 
         // parse url into generic JSON value
-        let root: Value = serde_json::from_str(input).ok()?;
+        let root: Value =
+            serde_json::from_str(input).map_err(|e| MetricError::Parse(e.to_string()))?;
 
         // access element using .get()
         let giturl: Option<&str> = root
@@ -30,48 +52,78 @@ impl Npm {
             .and_then(|value| value.as_str());
 
         // dereference the url so we can use .replace() later
-        let derefurl = giturl?;
+        let derefurl = giturl.ok_or_else(|| {
+            MetricError::Unsupported(format!("{} has no repository url", url))
+        })?;
 
         // Do not need to check if url contains git+, just do replace. That would take care of it
         let derefurl = derefurl.replace("git+", "");
         let derefurl = derefurl.replace(".git", "");
 
         // create github object
-        let output = Github::with_url(derefurl.as_str())?;
+        let output = Github::with_transport(derefurl.as_str(), config, transport)?.labeled(url);
 
         // return
-        Some(Npm {
-            gh: Box::new(output),
+        Ok(Npm {
+            gh: output,
+            registry: identity::from_registry(&root),
         })
     }
+
+    // score counts of the repository with the given curves instead of the defaults
+    pub fn with_curves(self, curves: Curves) -> Npm {
+        Npm {
+            gh: self.gh.with_curves(curves),
+            registry: self.registry,
+        }
+    }
+}
+
+// facts of the repository of the package
+impl Collector for Npm {
+    fn collect(&self) -> Result<RepositoryFacts> {
+        self.gh.collect()
+    }
+
+    // the registry knows the published name, the repository may hold several packages
+    fn identity(&self) -> Result<PackageIdentity> {
+        match &self.registry {
+            Some(registry) if registry.version.is_some() => Ok(registry.clone()),
+            Some(registry) => Ok(PackageIdentity {
+                name: registry.name.clone(),
+                ..self.gh.identity()?
+            }),
+            None => self.gh.identity(),
+        }
+    }
 }
 
 impl Metrics for Npm {
-    fn ramp_up_time(&self) -> f64 {
+    fn ramp_up_time(&self) -> Result<Evaluation> {
         self.gh.ramp_up_time()
     }
 
-    fn correctness(&self) -> f64 {
+    fn correctness(&self) -> Result<Evaluation> {
         self.gh.correctness()
     }
 
-    fn bus_factor(&self) -> f64 {
+    fn bus_factor(&self) -> Result<Evaluation> {
         self.gh.bus_factor()
     }
 
-    fn reviewed_code(&self) -> f64 {
+    fn reviewed_code(&self) -> Result<Evaluation> {
         self.gh.reviewed_code()
     }
 
-    fn responsiveness(&self) -> f64 {
+    fn responsiveness(&self) -> Result<Evaluation> {
         self.gh.responsiveness()
     }
 
-    fn compatibility(&self) -> f64 {
+    fn compatibility(&self) -> Result<Evaluation> {
         self.gh.compatibility()
     }
 
-    fn pinning_practice(&self) -> f64 {
+    fn pinning_practice(&self) -> Result<Evaluation> {
         self.gh.pinning_practice()
     }
 }
@@ -82,31 +134,150 @@ mod tests {
 
     #[test]
     fn no_url() {
-        assert!(Npm::with_url("").is_none());
+        assert!(Npm::with_url("").is_err());
     }
 
     #[test]
     fn bad_url() {
-        assert!(Npm::with_url("https://www.youtube.com/").is_none());
+        assert!(matches!(
+            Npm::with_url("https://www.youtube.com/"),
+            Err(MetricError::Unsupported(_))
+        ));
     }
 
     #[test]
     fn good_url() {
-        assert!(Npm::with_url("https://www.npmjs.com/package/js-yaml").is_some());
+        let stub = crate::http::tests::StubTransport::default()
+            .with(
+                "https://registry.npmjs.org/js-yaml",
+                200,
+                "{\"name\":\"js-yaml\",\"repository\":{\"url\":\"git+https://github.com/nodeca/js-yaml.git\"}}",
+            )
+            .with(
+                "https://api.github.com/graphql",
+                200,
+                &crate::metrics::github::facts_response(
+                    serde_json::json!({ "licenseInfo": { "spdxId": "MIT" } }),
+                ),
+            );
+        let n = Npm::with_transport(
+            "https://www.npmjs.com/package/js-yaml",
+            GithubConfig::for_host("github.com"),
+            Arc::new(stub),
+        )
+        .unwrap();
+        assert_eq!(Some(1.0), n.compatibility().unwrap().value());
+    }
+
+    #[test]
+    fn offline_url() {
+        let stub = crate::http::tests::StubTransport::default()
+            .with(
+                "https://registry.npmjs.org/left-pad",
+                200,
+                "{\"repository\":{\"url\":\"git+https://github.com/left-pad/left-pad.git\"}}",
+            )
+            .with(
+                "https://api.github.com/graphql",
+                200,
+                &crate::metrics::github::facts_response(
+                    serde_json::json!({ "licenseInfo": { "spdxId": "WTFPL" } }),
+                ),
+            );
+        let n = Npm::with_transport(
+            "https://www.npmjs.com/package/left-pad",
+            GithubConfig::for_host("github.com"),
+            Arc::new(stub),
+        )
+        .unwrap();
+        assert_eq!(Some(0.0), n.compatibility().unwrap().value());
+    }
+
+    #[test]
+    fn identity_from_registry() {
+        let npm = |document: &str| {
+            let stub = crate::http::tests::StubTransport::default()
+                .with("https://registry.npmjs.org/left-pad", 200, document)
+                .with(
+                    "https://api.github.com/graphql",
+                    200,
+                    &crate::metrics::github::facts_response(serde_json::json!({
+                        "releases": { "edges": [{ "node": { "tagName": "v1.1.0" } }] }
+                    })),
+                );
+            Npm::with_transport(
+                "https://www.npmjs.com/package/left-pad",
+                GithubConfig::for_host("github.com"),
+                Arc::new(stub),
+            )
+            .unwrap()
+        };
+
+        let id = npm("{\"name\":\"left-pad\",\"dist-tags\":{\"latest\":\"1.3.0\"},\"repository\":{\"url\":\"git+https://github.com/owner/repo.git\"}}")
+            .identity()
+            .unwrap();
+        assert_eq!(
+            ("left-pad", Some("1.3.0")),
+            (id.name.as_str(), id.version.as_deref())
+        );
+        assert_eq!(identity::Source::Registry, id.source);
+
+        // unpublished, the version of the repository
+        let id = npm("{\"name\":\"left-pad\",\"repository\":{\"url\":\"git+https://github.com/owner/repo.git\"}}")
+            .identity()
+            .unwrap();
+        assert_eq!(
+            ("left-pad", Some("1.1.0")),
+            (id.name.as_str(), id.version.as_deref())
+        );
+        assert_eq!(identity::Source::Release, id.source);
+    }
+
+    #[test]
+    fn unknown_package() {
+        let stub = crate::http::tests::StubTransport::default().with(
+            "https://registry.npmjs.org/no-such-package",
+            404,
+            "{\"error\":\"Not found\"}",
+        );
+        let n = Npm::with_transport(
+            "https://www.npmjs.com/package/no-such-package",
+            GithubConfig::for_host("github.com"),
+            Arc::new(stub),
+        );
+        assert!(matches!(n, Err(MetricError::NotFound(_))));
     }
 
     #[test]
     fn test_metrics() {
-        let n = Npm::with_url("https://www.npmjs.com/package/qs");
-        /*println!(
-            "{} {} {} {} {} {} {}",
-            n.ramp_up_time(),
-            n.correctness(),
-            n.bus_factor(),
-            n.responsiveness(),
-            n.compatibility(),
-            n.reviewed_code(),
-            n.pinning_practice()
-        );*/
+        let stub = crate::http::tests::StubTransport::default()
+            .with(
+                "https://registry.npmjs.org/qs",
+                200,
+                "{\"name\":\"qs\",\"repository\":{\"url\":\"git+https://github.com/ljharb/qs.git\"}}",
+            )
+            .with(
+                "https://api.github.com/graphql",
+                200,
+                &crate::metrics::github::facts_response(serde_json::json!({
+                    "issues": { "totalCount": 4 },
+                    "closedIssues": { "totalCount": 2 },
+                    "licenseInfo": { "spdxId": "BSD-3-Clause" }
+                })),
+            );
+        let n = Npm::with_transport(
+            "https://www.npmjs.com/package/qs",
+            GithubConfig::for_host("github.com"),
+            Arc::new(stub),
+        )
+        .unwrap();
+        // the metrics of the repository of the package
+        assert_eq!(Some(0.0), n.ramp_up_time().unwrap().value());
+        assert_eq!(Some(0.5), n.correctness().unwrap().value());
+        assert_eq!(Some(0.0), n.bus_factor().unwrap().value());
+        assert_eq!(Some(1.0), n.compatibility().unwrap().value());
+        assert!(n.responsiveness().unwrap().value().is_some());
+        assert!(n.reviewed_code().unwrap().value().is_none());
+        assert!(n.pinning_practice().unwrap().value().is_none());
     }
 }
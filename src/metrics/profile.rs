@@ -0,0 +1,269 @@
+// scoring profiles: named sets of net score weights, and the curves of the metrics
+//
+// Profiles are read from a TOML or JSON file (by extension), path set by METRICS_CONFIG
+// or --config. Profiles of the file are added to the built-in "default" profile and can
+// replace it. Curves not declared in the file keep their defaults, see curve.rs.
+//
+//   default_profile = "security"
+//
+//   [profiles.security]
+//   ramp_up = 0.0
+//   correctness = 0.1
+//   bus_factor = 0.2
+//   responsiveness = 0.2
+//   license = 0.3
+//   reviewed_code = 0.1
+//   pinning_practice = 0.1
+//
+//   [curves.bus_factor]
+//   type = "logistic"
+//   midpoint = 5.0
+//   steepness = 1.0
+//
+// Weights are keyed by metric id, must be non-negative and are normalized to sum to 1.
+// Metrics left out of a profile do not count in its net score.
+use crate::metrics::curve::Curves;
+use crate::metrics::registry::Registry;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+#[derive(Debug)]
+pub enum ProfileError {
+    Io(std::io::Error),
+    Parse(String),
+    // weights of the named profile, or a curve, are not valid
+    Invalid { profile: String, reason: String },
+    UnknownProfile(String),
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProfileError::Io(e) => write!(f, "failed to read config: {}", e),
+            ProfileError::Parse(e) => write!(f, "invalid config: {}", e),
+            ProfileError::Invalid { profile, reason } => {
+                write!(f, "invalid {}: {}", profile, reason)
+            }
+            ProfileError::UnknownProfile(name) => write!(f, "unknown profile {}", name),
+        }
+    }
+}
+
+impl std::error::Error for ProfileError {}
+
+impl From<std::io::Error> for ProfileError {
+    fn from(e: std::io::Error) -> Self {
+        ProfileError::Io(e)
+    }
+}
+
+// weight of each metric in the net score, by metric id, see registry.rs
+// metrics left out of a profile do not count in the net score
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Weights(pub BTreeMap<String, f64>);
+
+impl Default for Weights {
+    // weights of the registered metrics, normalized
+    fn default() -> Self {
+        Registry::default().default_weights().normalized().unwrap()
+    }
+}
+
+impl Weights {
+    pub fn get(&self, id: &str) -> f64 {
+        self.0.get(id).copied().unwrap_or(0.0)
+    }
+
+    // weights scaled to sum to 1, error if one is negative or all are zero
+    pub fn normalized(self) -> Result<Weights, String> {
+        if let Some((id, w)) = self.0.iter().find(|(_, w)| !w.is_finite() || **w < 0.0) {
+            return Err(format!("{} of {} is not a non-negative number", w, id));
+        }
+        let sum: f64 = self.0.values().sum();
+        if sum == 0.0 {
+            return Err("all weights are zero".to_string());
+        }
+        Ok(Weights(
+            self.0.into_iter().map(|(id, w)| (id, w / sum)).collect(),
+        ))
+    }
+}
+
+// contents of a config file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    default_profile: Option<String>,
+    #[serde(default)]
+    profiles: BTreeMap<String, Weights>,
+    #[serde(default)]
+    curves: Curves,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profiles {
+    pub default_profile: String,
+    pub profiles: BTreeMap<String, Weights>,
+    pub curves: Curves,
+}
+
+impl Default for Profiles {
+    fn default() -> Self {
+        Profiles {
+            default_profile: DEFAULT_PROFILE.to_string(),
+            profiles: BTreeMap::from([(DEFAULT_PROFILE.to_string(), Weights::default())]),
+            curves: Curves::default(),
+        }
+    }
+}
+
+impl Profiles {
+    // profiles of the file at METRICS_CONFIG, built-in profiles if it is not set
+    pub fn from_env() -> Result<Profiles, ProfileError> {
+        match std::env::var("METRICS_CONFIG") {
+            Ok(path) if !path.is_empty() => Profiles::load(path),
+            _ => Ok(Profiles::default()),
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Profiles, ProfileError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let json = path.extension().is_some_and(|e| e == "json");
+        Profiles::parse(&text, json)
+    }
+
+    // parse TOML, or JSON if `json` is set
+    pub fn parse(text: &str, json: bool) -> Result<Profiles, ProfileError> {
+        let file: ConfigFile = if json {
+            serde_json::from_str(text).map_err(|e| ProfileError::Parse(e.to_string()))?
+        } else {
+            toml::from_str(text).map_err(|e| ProfileError::Parse(e.to_string()))?
+        };
+
+        let mut profiles = Profiles::default();
+        for (name, weights) in file.profiles {
+            let weights = weights
+                .normalized()
+                .map_err(|reason| ProfileError::Invalid {
+                    profile: name.clone(),
+                    reason,
+                })?;
+            profiles.profiles.insert(name, weights);
+        }
+        file.curves
+            .validate()
+            .map_err(|(curve, reason)| ProfileError::Invalid {
+                profile: format!("curves.{}", curve),
+                reason,
+            })?;
+        profiles.curves = file.curves;
+        if let Some(name) = file.default_profile {
+            if !profiles.profiles.contains_key(&name) {
+                return Err(ProfileError::UnknownProfile(name));
+            }
+            profiles.default_profile = name;
+        }
+        Ok(profiles)
+    }
+
+    // weights of the named profile, or of the default profile
+    pub fn get(&self, name: Option<&str>) -> Result<Weights, ProfileError> {
+        let name = name.unwrap_or(&self.default_profile);
+        self.profiles
+            .get(name)
+            .cloned()
+            .ok_or_else(|| ProfileError::UnknownProfile(name.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_weights_normalized() {
+        let sum: f64 = Weights::default().0.values().sum();
+        assert!((sum - 1.0).abs() < 1e-9);
+        assert!((Weights::default().get("license") - 0.4 / 1.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn parse_toml() {
+        let profiles = Profiles::parse(
+            "default_profile = \"license\"\n\
+             [profiles.license]\n\
+             ramp_up = 0\ncorrectness = 0\nbus_factor = 0\nresponsiveness = 0\n\
+             license = 2\nreviewed_code = 0\npinning_practice = 0\n",
+            false,
+        )
+        .unwrap();
+        assert_eq!(1.0, profiles.get(None).unwrap().get("license"));
+        assert_eq!(Weights::default(), profiles.get(Some("default")).unwrap());
+        assert!(matches!(
+            profiles.get(Some("missing")),
+            Err(ProfileError::UnknownProfile(_))
+        ));
+    }
+
+    #[test]
+    fn parse_json() {
+        let profiles = Profiles::parse(
+            "{\"profiles\":{\"even\":{\"ramp_up\":1,\"correctness\":1,\"bus_factor\":1,\
+             \"responsiveness\":1,\"license\":1,\"reviewed_code\":1,\"pinning_practice\":1}}}",
+            true,
+        )
+        .unwrap();
+        assert_eq!(DEFAULT_PROFILE, profiles.default_profile);
+        assert!((profiles.get(Some("even")).unwrap().get("ramp_up") - 1.0 / 7.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn invalid_weights() {
+        let weights = |ramp_up: f64| {
+            format!(
+                "[profiles.bad]\nramp_up = {:?}\ncorrectness = 0\nbus_factor = 0\n\
+                 responsiveness = 0\nlicense = 0\nreviewed_code = 0\npinning_practice = 0\n",
+                ramp_up
+            )
+        };
+        assert!(matches!(
+            Profiles::parse(&weights(-1.0), false),
+            Err(ProfileError::Invalid { .. })
+        ));
+        assert!(matches!(
+            Profiles::parse(&weights(0.0), false),
+            Err(ProfileError::Invalid { .. })
+        ));
+        // metrics left out do not count
+        let profiles = Profiles::parse("[profiles.ramp_up]\nramp_up = 2\n", false).unwrap();
+        let weights = profiles.get(Some("ramp_up")).unwrap();
+        assert_eq!(1.0, weights.get("ramp_up"));
+        assert_eq!(0.0, weights.get("license"));
+        assert!(matches!(
+            Profiles::parse("default_profile = \"missing\"\n", false),
+            Err(ProfileError::UnknownProfile(_))
+        ));
+    }
+
+    #[test]
+    fn curves() {
+        let profiles = Profiles::parse(
+            "[curves.bus_factor]\ntype = \"logistic\"\nmidpoint = 5\nsteepness = 1\n",
+            false,
+        )
+        .unwrap();
+        assert!((profiles.curves.bus_factor.apply(5.0) - 0.5).abs() < 1e-9);
+        assert_eq!(Curves::default().ramp_up, profiles.curves.ramp_up);
+
+        match Profiles::parse("[curves.ramp_up]\ntype = \"peak\"\nat = 0\n", false) {
+            Err(ProfileError::Invalid { profile, .. }) => assert_eq!("curves.ramp_up", profile),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,237 @@
+// named metrics scored for every module, in output order
+//
+// A metric is an id used by profiles and explanations, a key in the output, a default
+// weight and a function of the module and its facts. Metrics are added with
+// Registry::register, the net score and the output pick them up without changes to the
+// Metrics trait, and can score facts no built-in metric looks at.
+use crate::metrics::facts::RepositoryFacts;
+use crate::metrics::profile::Weights;
+use crate::metrics::{Evaluation, Metrics, Result};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+pub type Compute = Arc<dyn Fn(&dyn Metrics, &RepositoryFacts) -> Result<Evaluation> + Send + Sync>;
+
+#[derive(Clone)]
+pub struct MetricDef {
+    // name in profiles and explanations, e.g. bus_factor
+    pub id: String,
+    // key in the output, e.g. BUS_FACTOR_SCORE
+    pub key: String,
+    // weight in the default profile, before normalization
+    pub weight: f64,
+    pub compute: Compute,
+}
+
+impl MetricDef {
+    pub fn new(
+        id: &str,
+        key: &str,
+        weight: f64,
+        compute: impl Fn(&dyn Metrics, &RepositoryFacts) -> Result<Evaluation> + Send + Sync + 'static,
+    ) -> MetricDef {
+        MetricDef {
+            id: id.to_string(),
+            key: key.to_string(),
+            weight,
+            compute: Arc::new(compute),
+        }
+    }
+}
+
+impl fmt::Debug for MetricDef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MetricDef")
+            .field("id", &self.id)
+            .field("key", &self.key)
+            .field("weight", &self.weight)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Registry {
+    metrics: Vec<MetricDef>,
+}
+
+impl Default for Registry {
+    // metrics of the Metrics trait, weights from part 2 of the project
+    fn default() -> Self {
+        Registry {
+            metrics: vec![
+                MetricDef::new("ramp_up", "RAMP_UP_SCORE", 0.05, |m, _| m.ramp_up_time()),
+                MetricDef::new("correctness", "CORRECTNESS_SCORE", 0.1, |m, _| {
+                    m.correctness()
+                }),
+                MetricDef::new("bus_factor", "BUS_FACTOR_SCORE", 0.1, |m, _| m.bus_factor()),
+                MetricDef::new("reviewed_code", "REVIEWED_CODE_SCORE", 0.2, |m, _| {
+                    m.reviewed_code()
+                }),
+                MetricDef::new(
+                    "responsiveness",
+                    "RESPONSIVE_MAINTAINER_SCORE",
+                    0.25,
+                    |m, _| m.responsiveness(),
+                ),
+                MetricDef::new("pinning_practice", "PINNING_PRACTICE_SCORE", 0.1, |m, _| {
+                    m.pinning_practice()
+                }),
+                MetricDef::new("license", "LICENSE_SCORE", 0.4, |m, _| m.compatibility()),
+            ],
+        }
+    }
+}
+
+impl Registry {
+    // error if the id or the output key is taken
+    pub fn register(&mut self, metric: MetricDef) -> std::result::Result<(), String> {
+        if let Some(m) = self
+            .metrics
+            .iter()
+            .find(|m| m.id == metric.id || m.key == metric.key)
+        {
+            return Err(format!("metric {} ({}) is already registered", m.id, m.key));
+        }
+        self.metrics.push(metric);
+        Ok(())
+    }
+
+    pub fn metrics(&self) -> &[MetricDef] {
+        &self.metrics
+    }
+
+    pub fn get(&self, id: &str) -> Option<&MetricDef> {
+        self.metrics.iter().find(|m| m.id == id)
+    }
+
+    // weights of the default profile
+    pub fn default_weights(&self) -> Weights {
+        Weights(
+            self.metrics
+                .iter()
+                .map(|m| (m.id.clone(), m.weight))
+                .collect::<BTreeMap<_, _>>(),
+        )
+    }
+
+    // error if the profile weighs a metric that is not registered
+    pub fn check(&self, weights: &Weights) -> std::result::Result<(), String> {
+        match weights.0.keys().find(|id| self.get(id).is_none()) {
+            Some(id) => Err(format!("unknown metric {}", id)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::github::offline_facts;
+    use crate::metrics::{Score, Scores};
+
+    struct Stub;
+    impl Metrics for Stub {
+        fn ramp_up_time(&self) -> Result<Evaluation> {
+            Ok(Score::Known(0.5).into())
+        }
+        fn correctness(&self) -> Result<Evaluation> {
+            Ok(Score::Known(1.0).into())
+        }
+        fn bus_factor(&self) -> Result<Evaluation> {
+            Ok(Score::Known(1.0).into())
+        }
+        fn responsiveness(&self) -> Result<Evaluation> {
+            Ok(Score::Known(1.0).into())
+        }
+        fn compatibility(&self) -> Result<Evaluation> {
+            Ok(Score::Known(1.0).into())
+        }
+        fn reviewed_code(&self) -> Result<Evaluation> {
+            Ok(Score::Known(1.0).into())
+        }
+        fn pinning_practice(&self) -> Result<Evaluation> {
+            Ok(Score::Known(1.0).into())
+        }
+    }
+
+    #[test]
+    fn builtin_metrics() {
+        let registry = Registry::default();
+        let keys: Vec<&str> = registry.metrics().iter().map(|m| m.key.as_str()).collect();
+        assert_eq!(
+            vec![
+                "RAMP_UP_SCORE",
+                "CORRECTNESS_SCORE",
+                "BUS_FACTOR_SCORE",
+                "REVIEWED_CODE_SCORE",
+                "RESPONSIVE_MAINTAINER_SCORE",
+                "PINNING_PRACTICE_SCORE",
+                "LICENSE_SCORE",
+            ],
+            keys
+        );
+        assert_eq!(Some(0.4), registry.get("license").map(|m| m.weight));
+    }
+
+    #[test]
+    fn third_party_metric() {
+        let mut registry = Registry::default();
+        registry
+            .register(MetricDef::new("halved", "HALVED_SCORE", 1.0, |m, _| {
+                let ramp_up = m.ramp_up_time()?.value().unwrap_or(0.0);
+                Ok(Evaluation::known(
+                    ramp_up / 2.0,
+                    "half of the ramp up score",
+                ))
+            }))
+            .unwrap();
+        let duplicate = MetricDef::new("halved", "OTHER_SCORE", 1.0, |m, _| m.bus_factor());
+        assert!(registry.register(duplicate).is_err());
+
+        let scores =
+            Scores::calculate(&Stub, &offline_facts(serde_json::json!({})), &registry).unwrap();
+        assert_eq!(Some(0.25), scores.get("halved").unwrap().value());
+        assert_eq!(8, scores.evaluations.len());
+        // only the new metric counts
+        let weights = Weights(BTreeMap::from([("halved".to_string(), 1.0)]));
+        assert_eq!(0.25, scores.net_score(&weights).score);
+    }
+
+    #[test]
+    fn metric_of_raw_facts() {
+        // releases are not scored by any built-in metric
+        let mut registry = Registry::default();
+        registry
+            .register(MetricDef::new(
+                "released",
+                "RELEASED_SCORE",
+                1.0,
+                |_, facts| {
+                    let releases = facts.repository.releases.edges.len();
+                    Ok(
+                        Evaluation::known(if releases > 0 { 1.0 } else { 0.0 }, "has a release")
+                            .evidence("releases", releases),
+                    )
+                },
+            ))
+            .unwrap();
+
+        let facts = offline_facts(serde_json::json!({
+            "releases": { "edges": [{ "node": { "tagName": "v1.0.0" } }] }
+        }));
+        let scores = Scores::calculate(&Stub, &facts, &registry).unwrap();
+        assert_eq!(Some(1.0), scores.get("released").unwrap().value());
+        let scores =
+            Scores::calculate(&Stub, &offline_facts(serde_json::json!({})), &registry).unwrap();
+        assert_eq!(Some(0.0), scores.get("released").unwrap().value());
+    }
+
+    #[test]
+    fn unknown_metric_weighed() {
+        let registry = Registry::default();
+        assert!(registry.check(&registry.default_weights()).is_ok());
+        let weights = Weights(BTreeMap::from([("bus_facter".to_string(), 1.0)]));
+        assert!(registry.check(&weights).is_err());
+    }
+}
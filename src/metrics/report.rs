@@ -0,0 +1,154 @@
+// scores of one module as written by the CLI and returned by the library and Python
+//
+// Serialized as one JSON object, see schema/score_report.v1.json:
+//
+//   {"URL":"https://github.com/owner/repo","NET_SCORE":0.81,"COVERAGE":0.92,
+//    "BUS_FACTOR_SCORE":0.5,"LICENSE_SCORE":null,...}
+//
+// Every metric of the registry has a key ending in _SCORE, null if unknown.
+// EXPLANATION is only present when asked for.
+use crate::metrics::profile::Weights;
+use crate::metrics::registry::Registry;
+use crate::metrics::{Score, Scores};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// version of schema/score_report.v1.json, changed when a field is removed or renamed
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoreReport {
+    #[serde(rename = "URL")]
+    pub url: String,
+    #[serde(rename = "NET_SCORE")]
+    pub net_score: f64,
+    // fraction of the total weight carried by known metrics
+    #[serde(rename = "COVERAGE")]
+    pub coverage: f64,
+    // score of every metric by output key, None if unknown
+    #[serde(flatten)]
+    pub metrics: BTreeMap<String, Option<f64>>,
+    // inputs and reasoning of every score by metric id
+    #[serde(
+        rename = "EXPLANATION",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub explanation: Option<serde_json::Value>,
+}
+
+impl ScoreReport {
+    pub fn new(url: &str, scores: &Scores, weights: &Weights, registry: &Registry) -> ScoreReport {
+        // weights of unknown metrics are spread over the known ones
+        let net = scores.net_score(weights);
+        let mut metrics = BTreeMap::new();
+        for metric in registry.metrics() {
+            let Some(evaluation) = scores.get(&metric.id) else {
+                continue;
+            };
+            debug!("{}: {}", metric.id, evaluation.explanation);
+            if let Score::Unknown(reason) = &evaluation.score {
+                info!("{} unknown: {}", metric.id, reason);
+            }
+            metrics.insert(metric.key.clone(), evaluation.value());
+        }
+        ScoreReport {
+            url: url.to_string(),
+            net_score: net.score,
+            coverage: net.coverage,
+            metrics,
+            explanation: Some(scores.explain()),
+        }
+    }
+
+    // score of a metric by output key, None if unknown or not scored
+    pub fn score(&self, key: &str) -> Option<f64> {
+        self.metrics.get(key).copied().flatten()
+    }
+
+    // scores with two decimals, as printed by the CLI
+    pub fn rounded(mut self) -> ScoreReport {
+        let round = |v: f64| (v * 100.0).round() / 100.0;
+        self.net_score = round(self.net_score);
+        self.coverage = round(self.coverage);
+        for score in self.metrics.values_mut() {
+            *score = score.map(round);
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Evaluation;
+
+    fn report() -> ScoreReport {
+        let registry = Registry::default();
+        let scores = Scores {
+            evaluations: registry
+                .metrics()
+                .iter()
+                .map(|m| match m.id.as_str() {
+                    "license" => (m.id.clone(), Evaluation::unknown("no license found")),
+                    _ => (m.id.clone(), Evaluation::known(2.0 / 3.0, "")),
+                })
+                .collect(),
+        };
+        ScoreReport::new(
+            "https://github.com/owner/repo",
+            &scores,
+            &Weights::default(),
+            &registry,
+        )
+    }
+
+    #[test]
+    fn serialized() {
+        let mut r = report().rounded();
+        r.explanation = None;
+        let json: serde_json::Value = serde_json::to_value(&r).unwrap();
+        assert_eq!("https://github.com/owner/repo", json["URL"]);
+        assert_eq!(0.67, json["NET_SCORE"]);
+        assert_eq!(0.67, json["REVIEWED_CODE_SCORE"]);
+        assert_eq!(serde_json::Value::Null, json["LICENSE_SCORE"]);
+        assert!(json.get("EXPLANATION").is_none());
+        assert_eq!(10, json.as_object().unwrap().len());
+
+        let parsed: ScoreReport = serde_json::from_value(json).unwrap();
+        assert_eq!(r, parsed);
+        assert_eq!(Some(0.67), parsed.score("BUS_FACTOR_SCORE"));
+        assert_eq!(None, parsed.score("LICENSE_SCORE"));
+    }
+
+    #[test]
+    fn explained() {
+        let r = report();
+        let json: serde_json::Value = serde_json::to_value(&r).unwrap();
+        assert_eq!(
+            "no license found",
+            json["EXPLANATION"]["license"]["explanation"]
+        );
+        assert_eq!(r, serde_json::from_value(json).unwrap());
+    }
+
+    #[test]
+    fn schema_lists_fields() {
+        let schema: serde_json::Value =
+            serde_json::from_str(include_str!("../../schema/score_report.v1.json")).unwrap();
+        assert!(schema["$id"]
+            .as_str()
+            .unwrap()
+            .ends_with(&format!("v{}.json", SCHEMA_VERSION)));
+        let json = serde_json::to_value(report()).unwrap();
+        let pattern = regex::Regex::new("^[A-Z_]+_SCORE$").unwrap();
+        for key in json.as_object().unwrap().keys() {
+            assert!(
+                schema["properties"].get(key).is_some() || pattern.is_match(key),
+                "{} is not in the schema",
+                key
+            );
+        }
+    }
+}
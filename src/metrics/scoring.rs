@@ -0,0 +1,352 @@
+// scoring formulas over collected facts, no network access
+//
+// Each function maps the facts of a repository to the evaluation of one metric.
+// Scorer scores saved facts through the Metrics trait, so they can be re-scored
+// with other weights or curves.
+use crate::metrics::curve::Curves;
+use crate::metrics::facts::{Collector, RepositoryFacts};
+use crate::metrics::semver::{self, Range, RangeKind};
+use crate::metrics::{Evaluation, Metrics, Result};
+use log::{debug, info};
+use std::collections::BTreeMap;
+
+pub fn ramp_up(facts: &RepositoryFacts, curves: &Curves) -> Evaluation {
+    // Check if there is readme
+    let readme = match facts
+        .repository
+        .readme
+        .as_ref()
+        .and_then(|r| r.text.as_ref())
+    {
+        Some(text) => text,
+        None => return Evaluation::known(0.0, "no README.md found").evidence("readme_lines", 0),
+    };
+
+    // Get the # of lines and calculate the score
+    info!("calculating ramp_up_score");
+    let lines = readme.lines().count();
+    let curve = &curves.ramp_up;
+    let result = Scorer::calc_ramp_up_time(lines.try_into().unwrap_or(u32::MAX), curve);
+    debug!("ramp_up_score: {:.2}", result);
+    Evaluation::known(
+        result,
+        format!("README.md has {} lines, scored by {}", lines, curve),
+    )
+    .evidence("readme_lines", lines)
+    .curve(curve)
+}
+
+pub fn correctness(facts: &RepositoryFacts) -> Evaluation {
+    info!("calculating correctness_score");
+    let all = facts.repository.issues.total_count;
+    let closed = facts.repository.closed_issues.total_count;
+    let result = Scorer::calc_correctness(all, closed);
+    debug!("correctness_score: {:.2}", result);
+    Evaluation::known(result, format!("{} of {} issues are closed", closed, all))
+        .evidence("issues", all)
+        .evidence("closed_issues", closed)
+}
+
+pub fn bus_factor(facts: &RepositoryFacts, curves: &Curves) -> Evaluation {
+    info!("calculating bus_factor_score");
+    let collaborators = facts.repository.mentionable_users.total_count;
+    // calculate the score for bus factor
+    let curve = &curves.bus_factor;
+    let score = curve.apply(collaborators as f64);
+    debug!("bus_factor_score: {:.2}", score);
+    Evaluation::known(
+        score,
+        format!("{} collaborators, scored by {}", collaborators, curve),
+    )
+    .evidence("collaborators", collaborators)
+    .curve(curve)
+}
+
+pub fn responsiveness(facts: &RepositoryFacts, curves: &Curves) -> Evaluation {
+    // pull requests updated last year
+    info!("calculating responsive_maintainer_score");
+    let pulls = facts.search.issue_count;
+
+    let curve = &curves.responsiveness;
+    let result = curve.apply(pulls as f64);
+    debug!("responsive_maintainer_score: {:.2}", result);
+    Evaluation::known(
+        result,
+        format!(
+            "{} pull requests updated in the last year, scored by {}",
+            pulls, curve
+        ),
+    )
+    .evidence("pull_requests_last_year", pulls)
+    .curve(curve)
+}
+
+pub fn compatibility(facts: &RepositoryFacts) -> Evaluation {
+    info!("calculating license_score");
+    let license = facts
+        .repository
+        .license_info
+        .as_ref()
+        .and_then(|l| l.spdx_id.as_deref());
+
+    // no license found
+    let Some(license) = license else {
+        return Evaluation::unknown("no license found");
+    };
+
+    let result = Scorer::calc_compatibility(license);
+    debug!("license_score: {:.2}", result);
+    let explanation = if result == 1.0 {
+        format!("{} is compatible with LGPLv2.1", license)
+    } else {
+        format!("{} is not compatible with LGPLv2.1", license)
+    };
+    Evaluation::known(result, explanation).evidence("license", license)
+}
+
+pub fn reviewed_code(facts: &RepositoryFacts) -> Evaluation {
+    // gets the fraction of project code that was introduced through pull requests with a code review
+    info!("calculating reviewed_code_score");
+    let pulls = &facts.repository.pull_requests.edges;
+    if pulls.is_empty() {
+        return Evaluation::unknown("no pull requests").evidence("pull_requests", 0);
+    }
+
+    let reviewed_pulls_count = pulls
+        .iter()
+        .filter(|pull| pull.node.reviews.total_count > 0)
+        .count();
+    Evaluation::known(
+        reviewed_pulls_count as f64 / pulls.len() as f64,
+        format!(
+            "{} of the last {} pull requests were reviewed",
+            reviewed_pulls_count,
+            pulls.len()
+        ),
+    )
+    .evidence("pull_requests", pulls.len())
+    .evidence("reviewed_pull_requests", reviewed_pulls_count)
+}
+
+pub fn pinning_practice(facts: &RepositoryFacts) -> Evaluation {
+    // use package.json of the default branch to get dependency count
+    info!("calculating pinning_practice_score");
+
+    // no dependencies to pin without a package.json
+    let has_package_json = facts.repository.tree.as_ref().is_some_and(|t| {
+        t.entries
+            .iter()
+            .any(|e| e.name == "package.json" && e.kind == "blob")
+    });
+    if !has_package_json {
+        return Evaluation::unknown("no package.json");
+    }
+
+    let Some(manifest) = facts
+        .repository
+        .package_json
+        .as_ref()
+        .and_then(|p| p.text.as_deref())
+        .and_then(|text| serde_json::from_str::<serde_json::Value>(text).ok())
+    else {
+        return Evaluation::unknown("package.json is not valid JSON");
+    };
+
+    // dependencies and dev dependencies with their version ranges, a package listed in
+    // both is counted in both
+    let empty = serde_json::Map::new();
+    let dependencies = manifest["dependencies"].as_object().unwrap_or(&empty);
+    let dev_dependencies = manifest["devDependencies"].as_object().unwrap_or(&empty);
+
+    // local packages are part of the project
+    let ranges: Vec<(&String, Range)> = dependencies
+        .iter()
+        .chain(dev_dependencies)
+        .map(|(name, spec)| match spec.as_str() {
+            Some(spec) => (name, semver::classify(spec)),
+            None => (name, Range::new(RangeKind::Invalid, false)),
+        })
+        .filter(|(_, range)| range.kind != RangeKind::Local)
+        .collect();
+    let pinned = ranges.iter().filter(|(_, range)| range.pinned).count();
+    let unpinned: Vec<&str> = ranges
+        .iter()
+        .filter(|(_, range)| !range.pinned)
+        .map(|(name, _)| name.as_str())
+        .collect();
+    let mut kinds = BTreeMap::new();
+    for (_, range) in &ranges {
+        *kinds.entry(range.kind.as_str()).or_insert(0) += 1;
+    }
+
+    let evaluation = if ranges.is_empty() {
+        Evaluation::known(1.0, "no dependencies to pin")
+    } else {
+        let result = pinned as f64 / ranges.len() as f64;
+        debug!("pinning_practice_score: {:.2}", result);
+        Evaluation::known(
+            result,
+            format!(
+                "{} of {} dependencies are pinned to a major.minor version",
+                pinned,
+                ranges.len()
+            ),
+        )
+    };
+    evaluation
+        .evidence("dependencies", dependencies.clone())
+        .evidence("dev_dependencies", dev_dependencies.clone())
+        .evidence("pinned", pinned)
+        .evidence("unpinned", unpinned)
+        .evidence("ranges", serde_json::json!(kinds))
+}
+
+// metrics of saved facts
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scorer {
+    facts: RepositoryFacts,
+    curves: Curves,
+}
+
+impl Scorer {
+    pub fn new(facts: RepositoryFacts) -> Scorer {
+        Scorer {
+            facts,
+            curves: Curves::default(),
+        }
+    }
+
+    // score counts with the given curves instead of the defaults
+    pub fn with_curves(mut self, curves: Curves) -> Scorer {
+        self.curves = curves;
+        self
+    }
+}
+
+impl Collector for Scorer {
+    fn collect(&self) -> Result<RepositoryFacts> {
+        Ok(self.facts.clone())
+    }
+}
+
+impl Metrics for Scorer {
+    fn ramp_up_time(&self) -> Result<Evaluation> {
+        Ok(ramp_up(&self.facts, &self.curves))
+    }
+
+    fn correctness(&self) -> Result<Evaluation> {
+        Ok(correctness(&self.facts))
+    }
+
+    fn bus_factor(&self) -> Result<Evaluation> {
+        Ok(bus_factor(&self.facts, &self.curves))
+    }
+
+    fn responsiveness(&self) -> Result<Evaluation> {
+        Ok(responsiveness(&self.facts, &self.curves))
+    }
+
+    fn compatibility(&self) -> Result<Evaluation> {
+        Ok(compatibility(&self.facts))
+    }
+
+    fn reviewed_code(&self) -> Result<Evaluation> {
+        Ok(reviewed_code(&self.facts))
+    }
+
+    fn pinning_practice(&self) -> Result<Evaluation> {
+        Ok(pinning_practice(&self.facts))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::curve::Curve;
+    use crate::metrics::github::{facts_response, offline_facts, offline_github};
+    use crate::metrics::registry::Registry;
+    use crate::metrics::Scores;
+
+    #[test]
+    fn pure_formulas() {
+        let f = offline_facts(serde_json::json!({
+            "issues": { "totalCount": 4 },
+            "closedIssues": { "totalCount": 3 },
+            "mentionableUsers": { "totalCount": 3 },
+            "licenseInfo": { "spdxId": "MIT" }
+        }));
+        assert_eq!(Some(0.75), correctness(&f).value());
+        assert_eq!(Some(0.5), bus_factor(&f, &Curves::default()).value());
+        assert_eq!(Some(1.0), compatibility(&f).value());
+        assert_eq!(Some(0.0), ramp_up(&f, &Curves::default()).value());
+        assert!(reviewed_code(&f).value().is_none());
+    }
+
+    #[test]
+    fn pinned_dependencies() {
+        let pinning = |package_json: &str| {
+            pinning_practice(&offline_facts(serde_json::json!({
+                "tree": { "entries": [{ "name": "package.json", "type": "blob" }] },
+                "packageJson": { "text": package_json }
+            })))
+        };
+
+        let e = pinning(
+            r#"{
+                "dependencies": { "a": "1.2.3", "b": "^1.2.3", "c": "file:../c", "d": 4 },
+                "devDependencies": { "e": "~1.2", "f": "latest" }
+            }"#,
+        );
+        assert_eq!(Some(0.4), e.value());
+        assert_eq!(
+            "2 of 5 dependencies are pinned to a major.minor version",
+            e.explanation
+        );
+        assert_eq!(serde_json::json!(["b", "d", "f"]), e.evidence["unpinned"]);
+        assert_eq!(1, e.evidence["ranges"]["invalid"]);
+        assert_eq!("file:../c", e.evidence["dependencies"]["c"]);
+        assert_eq!("latest", e.evidence["dev_dependencies"]["f"]);
+
+        // a dev dependency does not replace the range of the dependency
+        let e = pinning(
+            r#"{
+                "dependencies": { "a": "^1.2.3" },
+                "devDependencies": { "a": "1.2.3" }
+            }"#,
+        );
+        assert_eq!(Some(0.5), e.value());
+        assert_eq!("^1.2.3", e.evidence["dependencies"]["a"]);
+        assert_eq!(serde_json::json!(["a"]), e.evidence["unpinned"]);
+
+        assert_eq!(Some(1.0), pinning(r#"{ "name": "no-deps" }"#).value());
+        assert!(pinning("not json").value().is_none());
+    }
+
+    #[test]
+    fn rescored_offline() {
+        let body = facts_response(serde_json::json!({
+            "mentionableUsers": { "totalCount": 5 },
+            "licenseInfo": { "spdxId": "MIT" }
+        }));
+        let collected = offline_github(&body).collect().unwrap();
+
+        // saved facts give the same scores as the client that collected them
+        let saved = serde_json::to_string(&collected).unwrap();
+        let scorer = Scorer::new(serde_json::from_str(&saved).unwrap());
+        let registry = Registry::default();
+        assert_eq!(
+            Scores::calculate(&offline_github(&body), &collected, &registry).unwrap(),
+            Scores::calculate(&scorer, &collected, &registry).unwrap()
+        );
+
+        // and can be scored with other curves
+        let scorer = scorer.with_curves(Curves {
+            bus_factor: Curve::Logistic {
+                midpoint: 5.0,
+                steepness: 1.0,
+            },
+            ..Curves::default()
+        });
+        assert_eq!(Some(0.5), scorer.bus_factor().unwrap().value());
+    }
+}
@@ -0,0 +1,472 @@
+// npm dependency specifiers, see https://docs.npmjs.com/cli/configuring-npm/package-json#dependencies
+//
+// A range is pinned if every version it accepts has the same major.minor, so only
+// patch releases can change what is installed, e.g. 1.2.3, ~1.2.3, 1.2.x or ^0.2.3.
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeKind {
+    // 1.2.3, =1.2.3, v1.2.3
+    Exact,
+    // ~1.2.3
+    Tilde,
+    // ^1.2.3
+    Caret,
+    // 1.2.x, 1.x, 1.2
+    XRange,
+    // *, x or empty
+    Any,
+    // >=1.2.3 <2, 1.2.3 - 1.4, ranges joined by ||
+    Comparison,
+    // dist-tag such as latest or next
+    Tag,
+    // git+https://..., github:owner/repo, owner/repo, pinned by a full commit sha or a
+    // pinned #semver: range
+    Git,
+    // tarball url
+    Url,
+    // file:, link:, workspace: or a path, part of the project and not counted
+    Local,
+    Invalid,
+}
+
+impl RangeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RangeKind::Exact => "exact",
+            RangeKind::Tilde => "tilde",
+            RangeKind::Caret => "caret",
+            RangeKind::XRange => "x_range",
+            RangeKind::Any => "any",
+            RangeKind::Comparison => "comparison",
+            RangeKind::Tag => "tag",
+            RangeKind::Git => "git",
+            RangeKind::Url => "url",
+            RangeKind::Local => "local",
+            RangeKind::Invalid => "invalid",
+        }
+    }
+}
+
+impl fmt::Display for RangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Range {
+    pub kind: RangeKind,
+    // at most patch releases are accepted
+    pub pinned: bool,
+}
+
+impl Range {
+    pub fn new(kind: RangeKind, pinned: bool) -> Range {
+        Range { kind, pinned }
+    }
+}
+
+// kind of a dependency specifier and whether it is pinned to a major.minor version
+pub fn classify(spec: &str) -> Range {
+    let spec = spec.trim();
+
+    // npm:name@range installs another package under this name
+    if let Some(alias) = spec.strip_prefix("npm:") {
+        return match alias.rfind('@').filter(|&at| at > 0) {
+            Some(at) => classify(&alias[at + 1..]),
+            None => Range::new(RangeKind::Tag, false),
+        };
+    }
+    if ["file:", "link:", "workspace:", "portal:"]
+        .iter()
+        .any(|p| spec.starts_with(p))
+        || ["./", "../", "/", "~/"].iter().any(|p| spec.starts_with(p))
+    {
+        return Range::new(RangeKind::Local, true);
+    }
+    let git = ["git+", "git://", "github:", "gitlab:", "bitbucket:", "gist:"]
+        .iter()
+        .any(|p| spec.starts_with(p))
+        // owner/repo shorthand of GitHub, ranges never contain a slash
+        || (spec.contains('/') && !spec.contains(':'));
+    if git {
+        let pinned = match spec.split_once('#') {
+            Some((_, committish)) => match committish.strip_prefix("semver:") {
+                Some(range) => classify(range).pinned,
+                // branches and tags move, a commit does not
+                None => committish.len() == 40 && committish.chars().all(|c| c.is_ascii_hexdigit()),
+            },
+            None => false,
+        };
+        return Range::new(RangeKind::Git, pinned);
+    }
+    if spec.starts_with("http://") || spec.starts_with("https://") {
+        // a tarball is one build of the package
+        return Range::new(RangeKind::Url, true);
+    }
+
+    match parse(spec) {
+        Some(sets) => Range::new(kind(&sets), pinned(&sets)),
+        None if is_tag(spec) => Range::new(RangeKind::Tag, false),
+        None => Range::new(RangeKind::Invalid, false),
+    }
+}
+
+// major, minor and patch, prerelease and build metadata are ignored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Version(u64, u64, u64);
+
+// version with missing or wildcard parts, 1.2 or 1.x.x
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Partial {
+    major: Option<u64>,
+    minor: Option<u64>,
+    patch: Option<u64>,
+}
+
+impl Partial {
+    fn parse(text: &str) -> Option<Partial> {
+        let text = text.strip_prefix(['v', 'V']).unwrap_or(text);
+        // build metadata and prerelease
+        let text = text.split('+').next()?;
+        let text = text.split_once('-').map_or(text, |(version, _)| version);
+        let mut parts = Vec::new();
+        for part in text.split('.') {
+            parts.push(match part {
+                "x" | "X" | "*" => None,
+                _ if !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()) => {
+                    Some(part.parse().ok()?)
+                }
+                _ => return None,
+            });
+        }
+        if parts.len() > 3 {
+            return None;
+        }
+        parts.resize(3, None);
+        // nothing is fixed after a wildcard
+        if let Some(wildcard) = parts.iter().position(Option::is_none) {
+            parts[wildcard..].fill(None);
+        }
+        Some(Partial {
+            major: parts[0],
+            minor: parts[1],
+            patch: parts[2],
+        })
+    }
+
+    fn is_full(&self) -> bool {
+        self.patch.is_some()
+    }
+
+    // lowest version it matches
+    fn low(&self) -> Version {
+        Version(
+            self.major.unwrap_or(0),
+            self.minor.unwrap_or(0),
+            self.patch.unwrap_or(0),
+        )
+    }
+
+    // lowest version above every version it matches, None for *
+    fn next(&self) -> Option<Version> {
+        match (self.major, self.minor, self.patch) {
+            (Some(major), Some(minor), Some(patch)) => {
+                Some(Version(major, minor, patch.saturating_add(1)))
+            }
+            (Some(major), Some(minor), None) => Some(Version(major, minor.saturating_add(1), 0)),
+            (Some(major), None, _) => Some(Version(major.saturating_add(1), 0, 0)),
+            (None, _, _) => None,
+        }
+    }
+}
+
+// versions from `low` up to, not including, `high`, unbounded if None
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Bounds {
+    low: Version,
+    high: Option<Version>,
+}
+
+impl Bounds {
+    const ANY: Bounds = Bounds {
+        low: Version(0, 0, 0),
+        high: None,
+    };
+    const NONE: Bounds = Bounds {
+        low: Version(0, 0, 0),
+        high: Some(Version(0, 0, 0)),
+    };
+
+    fn at_least(low: Version) -> Bounds {
+        Bounds { low, high: None }
+    }
+
+    fn below(high: Option<Version>) -> Bounds {
+        Bounds {
+            low: Version(0, 0, 0),
+            high,
+        }
+    }
+
+    fn intersect(self, other: Bounds) -> Bounds {
+        let high = match (self.high, other.high) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        Bounds {
+            low: self.low.max(other.low),
+            high,
+        }
+    }
+
+    // every version has the major.minor of the lowest one
+    fn pinned(&self) -> bool {
+        let Version(major, minor, _) = self.low;
+        self.high
+            .is_some_and(|high| high <= Version(major, minor.saturating_add(1), 0))
+    }
+}
+
+// one comparator of a set, ^1.2.3 or >=1.2
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Comparator<'a> {
+    op: &'a str,
+    version: Partial,
+}
+
+impl Comparator<'_> {
+    fn bounds(&self) -> Bounds {
+        let v = self.version;
+        let Some(major) = v.major else {
+            // *, >=*, <=* match everything, <* and >* nothing
+            return match self.op {
+                "<" | ">" => Bounds::NONE,
+                _ => Bounds::ANY,
+            };
+        };
+        match self.op {
+            "" | "=" => Bounds {
+                low: v.low(),
+                high: v.next(),
+            },
+            ">=" => Bounds::at_least(v.low()),
+            ">" => match v.next() {
+                Some(next) => Bounds::at_least(next),
+                None => Bounds::NONE,
+            },
+            "<" => Bounds::below(Some(v.low())),
+            "<=" => Bounds::below(v.next()),
+            "~" => Bounds {
+                low: v.low(),
+                high: Some(match v.minor {
+                    Some(minor) => Version(major, minor.saturating_add(1), 0),
+                    None => Version(major.saturating_add(1), 0, 0),
+                }),
+            },
+            // the left-most non-zero part is fixed
+            _ => Bounds {
+                low: v.low(),
+                high: Some(match (major, v.minor, v.patch) {
+                    (0, Some(0), Some(patch)) => Version(0, 0, patch.saturating_add(1)),
+                    (0, Some(minor), _) => Version(0, minor.saturating_add(1), 0),
+                    (0, None, _) => Version(1, 0, 0),
+                    _ => Version(major.saturating_add(1), 0, 0),
+                }),
+            },
+        }
+    }
+}
+
+// comparator sets joined by ||, None if the range is not valid
+fn parse(spec: &str) -> Option<Vec<Vec<Comparator<'_>>>> {
+    spec.split("||").map(parse_set).collect()
+}
+
+fn parse_set(set: &str) -> Option<Vec<Comparator<'_>>> {
+    let tokens: Vec<&str> = set.split_whitespace().collect();
+    // 1.2.3 - 2.3.4
+    if let [from, "-", to] = tokens[..] {
+        return Some(vec![
+            Comparator {
+                op: ">=",
+                version: Partial::parse(from)?,
+            },
+            Comparator {
+                op: "<=",
+                version: Partial::parse(to)?,
+            },
+        ]);
+    }
+
+    let mut comparators = Vec::new();
+    let mut tokens = tokens.into_iter();
+    while let Some(token) = tokens.next() {
+        let op_len = token
+            .find(|c: char| !matches!(c, '<' | '>' | '=' | '~' | '^'))
+            .unwrap_or(token.len());
+        let (op, version) = token.split_at(op_len);
+        if !matches!(op, "" | "=" | "<" | "<=" | ">" | ">=" | "~" | "~>" | "^") {
+            return None;
+        }
+        // an operator may be separated from its version, >= 1.2.3
+        let version = match version {
+            "" => tokens.next()?,
+            _ => version,
+        };
+        let op = if op == "~>" { "~" } else { op };
+        comparators.push(Comparator {
+            op,
+            version: Partial::parse(version)?,
+        });
+    }
+    // an empty set matches everything
+    if comparators.is_empty() {
+        comparators.push(Comparator {
+            op: "",
+            version: Partial {
+                major: None,
+                minor: None,
+                patch: None,
+            },
+        });
+    }
+    Some(comparators)
+}
+
+// every set accepts patch releases of the same major.minor only
+fn pinned(sets: &[Vec<Comparator>]) -> bool {
+    let bounds: Vec<Bounds> = sets
+        .iter()
+        .map(|set| {
+            set.iter()
+                .map(Comparator::bounds)
+                .fold(Bounds::ANY, Bounds::intersect)
+        })
+        .collect();
+    let minor = |b: &Bounds| (b.low.0, b.low.1);
+    bounds.iter().all(Bounds::pinned) && bounds.iter().all(|b| minor(b) == minor(&bounds[0]))
+}
+
+fn kind(sets: &[Vec<Comparator>]) -> RangeKind {
+    let [set] = sets else {
+        return RangeKind::Comparison;
+    };
+    // a hyphen range is two comparators
+    let [comparator] = set[..] else {
+        return RangeKind::Comparison;
+    };
+    match comparator.op {
+        "" | "=" if comparator.version.major.is_none() => RangeKind::Any,
+        "~" => RangeKind::Tilde,
+        "^" => RangeKind::Caret,
+        "" | "=" if comparator.version.is_full() => RangeKind::Exact,
+        "" | "=" => RangeKind::XRange,
+        _ => RangeKind::Comparison,
+    }
+}
+
+// dist-tags are names that are not versions, e.g. latest or next
+fn is_tag(spec: &str) -> bool {
+    spec.starts_with(|c: char| c.is_ascii_alphabetic())
+        && spec
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kinds_of_ranges() {
+        let cases = [
+            ("1.2.3", RangeKind::Exact, true),
+            ("=1.2.3", RangeKind::Exact, true),
+            ("v1.2.3", RangeKind::Exact, true),
+            ("1.2.3-beta.2+build.5", RangeKind::Exact, true),
+            ("~1.2.3", RangeKind::Tilde, true),
+            ("~1.2", RangeKind::Tilde, true),
+            ("~>1.2.3", RangeKind::Tilde, true),
+            ("~1", RangeKind::Tilde, false),
+            ("^1.2.3", RangeKind::Caret, false),
+            ("^0.2.3", RangeKind::Caret, true),
+            ("^0.0.3", RangeKind::Caret, true),
+            ("^0.0", RangeKind::Caret, true),
+            ("^0", RangeKind::Caret, false),
+            ("^1.x", RangeKind::Caret, false),
+            ("1.2.x", RangeKind::XRange, true),
+            ("1.2.*", RangeKind::XRange, true),
+            ("1.2", RangeKind::XRange, true),
+            ("1.x", RangeKind::XRange, false),
+            ("1", RangeKind::XRange, false),
+            ("1.x.3", RangeKind::XRange, false),
+            ("*", RangeKind::Any, false),
+            ("x", RangeKind::Any, false),
+            ("", RangeKind::Any, false),
+            (">=1.2.3", RangeKind::Comparison, false),
+            (">= 1.2.3 < 1.3.0", RangeKind::Comparison, true),
+            (">=1.2.3 <=1.2.9", RangeKind::Comparison, true),
+            (">1.2.3 <1.4", RangeKind::Comparison, false),
+            ("<1.2.3", RangeKind::Comparison, false),
+            ("1.2.3 - 1.2.9", RangeKind::Comparison, true),
+            ("1.2.3 - 1.2", RangeKind::Comparison, true),
+            ("1.2.3 - 1.3", RangeKind::Comparison, false),
+            ("1.2.3 || 1.2.5", RangeKind::Comparison, true),
+            ("1.2.3 || 1.3.0", RangeKind::Comparison, false),
+            ("^1.2.3 || ~1.2.5", RangeKind::Comparison, false),
+            ("latest", RangeKind::Tag, false),
+            ("next-2", RangeKind::Tag, false),
+            ("not a range", RangeKind::Invalid, false),
+            ("1.2.3.4", RangeKind::Invalid, false),
+            (">>1.2.3", RangeKind::Invalid, false),
+        ];
+        for (spec, kind, pinned) in cases {
+            assert_eq!(Range::new(kind, pinned), classify(spec), "{:?}", spec);
+        }
+    }
+
+    #[test]
+    fn kinds_of_specifiers() {
+        let cases = [
+            (
+                "git+https://github.com/owner/repo.git",
+                RangeKind::Git,
+                false,
+            ),
+            (
+                "git+ssh://git@github.com/owner/repo.git#v1.2.3",
+                RangeKind::Git,
+                false,
+            ),
+            (
+                "git://github.com/owner/repo.git#4b825dc642cb6eb9a060e54bf8d69288fbee4904",
+                RangeKind::Git,
+                true,
+            ),
+            (
+                "git://github.com/owner/repo.git#4b825dc6",
+                RangeKind::Git,
+                false,
+            ),
+            ("github:owner/repo#semver:^1.2.3", RangeKind::Git, false),
+            ("github:owner/repo#semver:~1.2.3", RangeKind::Git, true),
+            ("github:owner/repo#semver:1.2.3", RangeKind::Git, true),
+            ("owner/repo", RangeKind::Git, false),
+            ("owner/repo#main", RangeKind::Git, false),
+            ("https://example.com/pkg-1.2.3.tgz", RangeKind::Url, true),
+            ("file:../pkg", RangeKind::Local, true),
+            ("link:../pkg", RangeKind::Local, true),
+            ("workspace:*", RangeKind::Local, true),
+            ("../pkg", RangeKind::Local, true),
+            ("~/pkg", RangeKind::Local, true),
+            ("npm:@scope/pkg@~1.2.3", RangeKind::Tilde, true),
+            ("npm:pkg@^1.2.3", RangeKind::Caret, false),
+            ("npm:pkg", RangeKind::Tag, false),
+        ];
+        for (spec, kind, pinned) in cases {
+            assert_eq!(Range::new(kind, pinned), classify(spec), "{:?}", spec);
+        }
+    }
+}
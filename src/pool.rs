@@ -0,0 +1,88 @@
+// bounded parallelism for scoring many packages
+//
+// The number of workers is read from METRICS_WORKERS (default 4). GitHub discourages
+// many concurrent requests with its secondary rate limits, so keep it small.
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+pub const DEFAULT_WORKERS: usize = 4;
+
+pub fn workers_from_env() -> usize {
+    std::env::var("METRICS_WORKERS")
+        .ok()
+        .and_then(|w| w.parse::<usize>().ok())
+        .filter(|w| *w > 0)
+        .unwrap_or(DEFAULT_WORKERS)
+}
+
+// apply f to every item on at most `workers` threads, results keep the order of items
+pub fn parallel_map<T, R, F>(items: Vec<T>, workers: usize, f: F) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Fn(T) -> R + Sync,
+{
+    let workers = workers.clamp(1, items.len().max(1));
+    if workers == 1 {
+        return items.into_iter().map(f).collect();
+    }
+
+    let items: Vec<Mutex<Option<T>>> = items.into_iter().map(|i| Mutex::new(Some(i))).collect();
+    let results: Vec<Mutex<Option<R>>> = items.iter().map(|_| Mutex::new(None)).collect();
+    let next = AtomicUsize::new(0);
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                if i >= items.len() {
+                    break;
+                }
+                let item = items[i].lock().unwrap().take().unwrap();
+                let result = f(item);
+                *results[i].lock().unwrap() = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_iter()
+        .map(|r| r.into_inner().unwrap().unwrap())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn keeps_order() {
+        let items: Vec<u64> = (0..50).collect();
+        let results = parallel_map(items, 8, |i| {
+            // later items finish first
+            std::thread::sleep(std::time::Duration::from_millis(50 - i));
+            i * 2
+        });
+        assert_eq!((0..50).map(|i| i * 2).collect::<Vec<_>>(), results);
+    }
+
+    #[test]
+    fn bounded_workers() {
+        let running = AtomicUsize::new(0);
+        let max = AtomicUsize::new(0);
+        parallel_map((0..20).collect(), 3, |_: u32| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            max.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            running.fetch_sub(1, Ordering::SeqCst);
+        });
+        assert!(max.load(Ordering::SeqCst) <= 3);
+    }
+
+    #[test]
+    fn empty_and_single_worker() {
+        assert!(parallel_map(Vec::<u32>::new(), 4, |i| i).is_empty());
+        assert_eq!(vec![1, 2], parallel_map(vec![0, 1], 0, |i| i + 1));
+    }
+}
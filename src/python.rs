@@ -0,0 +1,873 @@
+// Python module, built with maturin
+//
+//   import metricslib
+//   try:
+//       report = metricslib.calcscore_py("https://github.com/owner/repo")
+//       print(report.net_score, report.bus_factor, report.explanation("bus_factor"))
+//   except metricslib.NotFound:
+//       ...
+//
+//   client = metricslib.Client(metricslib.Config(token=tenant_token, profile="security"))
+//   reports, errors = client.score_many(urls)
+//   print(client.identity("https://www.npmjs.com/package/left-pad").version)
+//
+// Failures raise a subclass of MetricsError, invalid profiles or config files ValueError.
+
+// code generated by the pyo3 0.18 macros trips lints of newer compilers
+#![allow(unexpected_cfgs, non_local_definitions)]
+
+use crate::http::{self, cache, rate_limit::RateLimits};
+use crate::metrics::curve::Curves;
+use crate::metrics::github::GithubConfig;
+use crate::metrics::identity::PackageIdentity;
+use crate::metrics::profile::Weights;
+use crate::metrics::registry::Registry;
+use crate::metrics::report::SCHEMA_VERSION;
+use crate::{collector, score_package, MetricError, ScoreReport, ScoringConfig};
+use pyo3::create_exception;
+use pyo3::exceptions::{PyAttributeError, PyException, PyValueError};
+use pyo3::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::time::Duration;
+
+create_exception!(
+    metricslib,
+    MetricsError,
+    PyException,
+    "A package could not be scored."
+);
+create_exception!(
+    metricslib,
+    InvalidUrl,
+    MetricsError,
+    "Not a GitHub repository or npm package url."
+);
+create_exception!(
+    metricslib,
+    NotFound,
+    MetricsError,
+    "The package or repository does not exist."
+);
+create_exception!(
+    metricslib,
+    RateLimited,
+    MetricsError,
+    "The GitHub API budget is exhausted."
+);
+
+fn to_py_err(e: MetricError) -> PyErr {
+    match e {
+        MetricError::Unsupported(_) => InvalidUrl::new_err(e.to_string()),
+        MetricError::NotFound(_) => NotFound::new_err(e.to_string()),
+        MetricError::RateLimited { .. } => RateLimited::new_err(e.to_string()),
+        MetricError::Network(_) | MetricError::Auth(_) | MetricError::Parse(_) => {
+            MetricsError::new_err(e.to_string())
+        }
+    }
+}
+
+// scores of one package, metrics are attributes named by id, e.g. report.bus_factor
+#[pyclass(name = "ScoreReport", module = "metricslib")]
+#[derive(Debug, Clone)]
+pub struct PyScoreReport {
+    report: ScoreReport,
+    // (id, output key) of every metric
+    metrics: Vec<(String, String)>,
+}
+
+impl PyScoreReport {
+    pub fn new(report: ScoreReport, registry: &Registry) -> PyScoreReport {
+        let metrics = registry
+            .metrics()
+            .iter()
+            .map(|m| (m.id.clone(), m.key.clone()))
+            .collect();
+        PyScoreReport { report, metrics }
+    }
+
+    fn key(&self, id: &str) -> Option<&str> {
+        self.metrics
+            .iter()
+            .find(|(i, _)| i == id)
+            .map(|(_, key)| key.as_str())
+    }
+}
+
+#[pymethods]
+impl PyScoreReport {
+    #[getter]
+    fn url(&self) -> &str {
+        &self.report.url
+    }
+
+    #[getter]
+    fn net_score(&self) -> f64 {
+        self.report.net_score
+    }
+
+    #[getter]
+    fn coverage(&self) -> f64 {
+        self.report.coverage
+    }
+
+    // metric ids in output order
+    #[getter]
+    fn metrics(&self) -> Vec<&str> {
+        self.metrics.iter().map(|(id, _)| id.as_str()).collect()
+    }
+
+    // score of a metric by id, None if unknown
+    fn __getattr__(&self, name: &str) -> PyResult<Option<f64>> {
+        match self.key(name) {
+            Some(key) => Ok(self.report.score(key)),
+            None => Err(PyAttributeError::new_err(format!(
+                "ScoreReport has no attribute {}",
+                name
+            ))),
+        }
+    }
+
+    // human readable reasoning behind a score, the reason if it is unknown
+    fn explanation(&self, metric: &str) -> Option<String> {
+        let explanation = self.report.explanation.as_ref()?;
+        explanation[metric]["explanation"]
+            .as_str()
+            .map(str::to_string)
+    }
+
+    // same keys as a line of the CLI output with --explain
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyAny> {
+        py.import("json")?.call_method1("loads", (self.to_json(),))
+    }
+
+    fn to_json(&self) -> String {
+        serde_json::to_string(&self.report).unwrap()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "ScoreReport(url={:?}, net_score={:.2}, coverage={:.2})",
+            self.report.url, self.report.net_score, self.report.coverage
+        )
+    }
+}
+
+// published name and version of a package
+#[pyclass(name = "PackageIdentity", module = "metricslib")]
+#[derive(Debug, Clone)]
+pub struct PyPackageIdentity {
+    identity: PackageIdentity,
+}
+
+#[pymethods]
+impl PyPackageIdentity {
+    #[getter]
+    fn name(&self) -> &str {
+        &self.identity.name
+    }
+
+    // None if nothing was published, released or tagged
+    #[getter]
+    fn version(&self) -> Option<&str> {
+        self.identity.version.as_deref()
+    }
+
+    // registry, package_json, release, tag or repository
+    #[getter]
+    fn source(&self) -> &str {
+        self.identity.source.as_str()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "PackageIdentity(name={:?}, version={:?}, source={:?})",
+            self.identity.name,
+            self.identity.version,
+            self.identity.source.as_str()
+        )
+    }
+}
+
+// identity of one package, other Python threads run while waiting for the network
+fn identify(py: Python, url: &str, config: &ScoringConfig) -> PyResult<PyPackageIdentity> {
+    let identity = py
+        .allow_threads(|| crate::package_identity(url, config))
+        .map_err(to_py_err)?;
+    Ok(PyPackageIdentity { identity })
+}
+
+// identity with the GitHub settings of the environment, profiles are not needed
+fn identify_from_env(py: Python, url: &str) -> PyResult<PackageIdentity> {
+    let (github, transport) = (GithubConfig::from_env(), http::transport_from_env());
+    py.allow_threads(|| collector(url, &github, &transport)?.identity())
+        .map_err(to_py_err)
+}
+
+// weights of the profile and curves, from the config file if given
+fn scoring_config(profile: Option<&str>, config: Option<&str>) -> PyResult<ScoringConfig> {
+    ScoringConfig::load(config, profile).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+// report of one package, other Python threads run while waiting for the network
+fn score_one(py: Python, url: &str, config: &ScoringConfig) -> PyResult<PyScoreReport> {
+    let report = py
+        .allow_threads(|| score_package(url, config))
+        .map_err(to_py_err)?;
+    Ok(PyScoreReport::new(report, &config.registry))
+}
+
+// reports of the packages that were scored, in the order of urls, and the exception
+// of every url that failed
+fn score_batch(
+    py: Python,
+    urls: &[String],
+    config: &ScoringConfig,
+) -> (Vec<PyScoreReport>, HashMap<String, PyObject>) {
+    let results = py.allow_threads(|| crate::score_many(urls, config));
+
+    let mut reports = Vec::new();
+    let mut errors = HashMap::new();
+    for (url, result) in results {
+        match result {
+            Ok(report) => reports.push(PyScoreReport::new(report, &config.registry)),
+            Err(e) => {
+                errors.insert(url, to_py_err(e).into_value(py).into_py(py));
+            }
+        }
+    }
+    (reports, errors)
+}
+
+#[pyfunction]
+#[pyo3(signature = (url, profile = None, config = None))]
+pub fn calcscore_py(
+    py: Python,
+    url: &str,
+    profile: Option<&str>,
+    config: Option<&str>,
+) -> PyResult<PyScoreReport> {
+    score_one(py, url, &scoring_config(profile, config)?)
+}
+
+#[pyfunction]
+#[pyo3(signature = (urls, workers = None, profile = None, config = None))]
+pub fn score_many(
+    py: Python,
+    urls: Vec<String>,
+    workers: Option<usize>,
+    profile: Option<&str>,
+    config: Option<&str>,
+) -> PyResult<(Vec<PyScoreReport>, HashMap<String, PyObject>)> {
+    let mut config = scoring_config(profile, config)?;
+    if let Some(workers) = workers {
+        config.workers = workers;
+    }
+    Ok(score_batch(py, &urls, &config))
+}
+
+// scoring settings of a Client, e.g. one per tenant of a web service
+// settings that are not given are read from the environment, like the free functions do
+#[pyclass(name = "Config", module = "metricslib")]
+#[derive(Debug, Clone)]
+pub struct PyConfig {
+    scoring: ScoringConfig,
+    // directory responses are cached in, and their TTL
+    cache: Option<(PathBuf, Duration)>,
+}
+
+impl PyConfig {
+    fn from_env() -> PyResult<PyConfig> {
+        Ok(PyConfig {
+            scoring: scoring_config(None, None)?,
+            cache: http::cache_from_env(),
+        })
+    }
+}
+
+#[pymethods]
+impl PyConfig {
+    #[new]
+    #[pyo3(signature = (
+        *,
+        profile = None,
+        config = None,
+        weights = None,
+        curves = None,
+        token = None,
+        hosts = None,
+        api_url = None,
+        graphql_url = None,
+        cache_dir = None,
+        cache_ttl = None,
+        workers = None
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        py: Python,
+        profile: Option<&str>,
+        config: Option<&str>,
+        weights: Option<BTreeMap<String, f64>>,
+        curves: Option<&PyAny>,
+        token: Option<String>,
+        hosts: Option<Vec<String>>,
+        api_url: Option<String>,
+        graphql_url: Option<String>,
+        cache_dir: Option<PathBuf>,
+        cache_ttl: Option<u64>,
+        workers: Option<usize>,
+    ) -> PyResult<PyConfig> {
+        let mut scoring = scoring_config(profile, config)?;
+        // replace the weights of the profile
+        if let Some(weights) = weights {
+            let weights = Weights(weights)
+                .normalized()
+                .map_err(|e| PyValueError::new_err(format!("invalid weights: {}", e)))?;
+            scoring
+                .registry
+                .check(&weights)
+                .map_err(|e| PyValueError::new_err(format!("invalid weights: {}", e)))?;
+            scoring.weights = weights;
+        }
+        // same layout as the [curves] table of a config file
+        if let Some(curves) = curves {
+            let json: String = py
+                .import("json")?
+                .call_method1("dumps", (curves,))?
+                .extract()?;
+            let curves: Curves = serde_json::from_str(&json)
+                .map_err(|e| PyValueError::new_err(format!("invalid curves: {}", e)))?;
+            curves.validate().map_err(|(curve, reason)| {
+                PyValueError::new_err(format!("invalid curves.{}: {}", curve, reason))
+            })?;
+            scoring.curves = curves;
+        }
+
+        // endpoints of the first host unless given
+        if let Some(hosts) = hosts {
+            let host = hosts
+                .first()
+                .ok_or_else(|| PyValueError::new_err("hosts is empty"))?;
+            let token = scoring.github.token.take();
+            scoring.github = GithubConfig::for_host(&host.to_lowercase());
+            scoring.github.hosts = hosts.iter().map(|h| h.to_lowercase()).collect();
+            scoring.github.token = token;
+        }
+        if let Some(api_url) = api_url {
+            scoring.github.api_url = api_url.trim_end_matches('/').to_string();
+        }
+        if let Some(graphql_url) = graphql_url {
+            scoring.github.graphql_url = graphql_url;
+        }
+        if token.is_some() {
+            scoring.github.token = token;
+        }
+
+        if let Some(workers) = workers {
+            scoring.workers = workers;
+        }
+        let cache = match cache_dir {
+            Some(dir) => Some((
+                dir,
+                cache_ttl
+                    .map(Duration::from_secs)
+                    .unwrap_or(cache::DEFAULT_TTL),
+            )),
+            None => http::cache_from_env(),
+        };
+        Ok(PyConfig { scoring, cache })
+    }
+
+    // normalized weights by metric id
+    #[getter]
+    fn weights(&self) -> BTreeMap<String, f64> {
+        self.scoring.weights.0.clone()
+    }
+
+    #[getter]
+    fn curves<'py>(&self, py: Python<'py>) -> PyResult<&'py PyAny> {
+        let json = serde_json::to_string(&self.scoring.curves).unwrap();
+        py.import("json")?.call_method1("loads", (json,))
+    }
+
+    #[getter]
+    fn hosts(&self) -> Vec<String> {
+        self.scoring.github.hosts.clone()
+    }
+
+    #[getter]
+    fn api_url(&self) -> &str {
+        &self.scoring.github.api_url
+    }
+
+    #[getter]
+    fn graphql_url(&self) -> &str {
+        &self.scoring.github.graphql_url
+    }
+
+    // the token itself is not readable back
+    #[getter]
+    fn has_token(&self) -> bool {
+        self.scoring.github.token.is_some()
+    }
+
+    #[getter]
+    fn cache_dir(&self) -> Option<String> {
+        self.cache
+            .as_ref()
+            .map(|(dir, _)| dir.to_string_lossy().to_string())
+    }
+
+    #[getter]
+    fn cache_ttl(&self) -> Option<u64> {
+        self.cache.as_ref().map(|(_, ttl)| ttl.as_secs())
+    }
+
+    #[getter]
+    fn workers(&self) -> usize {
+        self.scoring.workers
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Config(hosts={:?}, api_url={:?}, has_token={}, cache_dir={:?}, workers={})",
+            self.scoring.github.hosts,
+            self.scoring.github.api_url,
+            self.has_token(),
+            self.cache_dir(),
+            self.scoring.workers
+        )
+    }
+}
+
+// scores packages with one Config, reusing connections, the response cache and the
+// API budget of its token across calls
+#[pyclass(name = "Client", module = "metricslib")]
+#[derive(Debug)]
+pub struct PyClient {
+    config: PyConfig,
+    scoring: ScoringConfig,
+}
+
+#[pymethods]
+impl PyClient {
+    #[new]
+    #[pyo3(signature = (config = None))]
+    fn new(config: Option<PyConfig>) -> PyResult<PyClient> {
+        let config = match config {
+            Some(config) => config,
+            None => PyConfig::from_env()?,
+        };
+        let mut scoring = config.scoring.clone();
+        // budgets are kept per token, clients of the same token share them
+        scoring.transport = http::transport_with(config.cache.clone(), RateLimits::global());
+        Ok(PyClient { config, scoring })
+    }
+
+    #[getter]
+    fn config(&self) -> PyConfig {
+        self.config.clone()
+    }
+
+    fn score(&self, py: Python, url: &str) -> PyResult<PyScoreReport> {
+        score_one(py, url, &self.scoring)
+    }
+
+    fn identity(&self, py: Python, url: &str) -> PyResult<PyPackageIdentity> {
+        identify(py, url, &self.scoring)
+    }
+
+    #[pyo3(name = "score_many", signature = (urls, workers = None))]
+    fn score_many_py(
+        &self,
+        py: Python,
+        urls: Vec<String>,
+        workers: Option<usize>,
+    ) -> (Vec<PyScoreReport>, HashMap<String, PyObject>) {
+        match workers {
+            Some(workers) => {
+                let mut scoring = self.scoring.clone();
+                scoring.workers = workers;
+                score_batch(py, &urls, &scoring)
+            }
+            None => score_batch(py, &urls, &self.scoring),
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Client({})", self.config.__repr__())
+    }
+}
+
+#[pyfunction]
+pub fn package_identity(py: Python, url: &str) -> PyResult<PyPackageIdentity> {
+    let identity = identify_from_env(py, url)?;
+    Ok(PyPackageIdentity { identity })
+}
+
+#[pyfunction]
+pub fn get_name_py(py: Python, url: &str) -> PyResult<String> {
+    Ok(identify_from_env(py, url)?.name)
+}
+
+// "0.0.0" if nothing was published, released or tagged
+#[pyfunction]
+pub fn get_version_py(py: Python, url: &str) -> PyResult<String> {
+    Ok(identify_from_env(py, url)?
+        .version
+        .unwrap_or_else(|| "0.0.0".to_string()))
+}
+
+#[pymodule]
+fn metricslib(py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(calcscore_py, m)?)?;
+    m.add_function(wrap_pyfunction!(score_many, m)?)?;
+    m.add_function(wrap_pyfunction!(package_identity, m)?)?;
+    m.add_function(wrap_pyfunction!(get_name_py, m)?)?;
+    m.add_function(wrap_pyfunction!(get_version_py, m)?)?;
+    m.add_class::<PyScoreReport>()?;
+    m.add_class::<PyPackageIdentity>()?;
+    m.add_class::<PyConfig>()?;
+    m.add_class::<PyClient>()?;
+    m.add("MetricsError", py.get_type::<MetricsError>())?;
+    m.add("InvalidUrl", py.get_type::<InvalidUrl>())?;
+    m.add("NotFound", py.get_type::<NotFound>())?;
+    m.add("RateLimited", py.get_type::<RateLimited>())?;
+    m.add("SCORE_REPORT_SCHEMA_VERSION", SCHEMA_VERSION)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::cache::CachingTransport;
+    use crate::http::{HttpRequest, HttpResponse, Transport};
+    use crate::metrics::{Evaluation, Scores};
+    use pyo3::types::IntoPyDict;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn report() -> PyScoreReport {
+        let registry = Registry::default();
+        let scores = Scores {
+            evaluations: registry
+                .metrics()
+                .iter()
+                .map(|m| match m.id.as_str() {
+                    "license" => (m.id.clone(), Evaluation::unknown("no license found")),
+                    _ => (m.id.clone(), Evaluation::known(0.5, "")),
+                })
+                .collect(),
+        };
+        let report = ScoreReport::new(
+            "https://github.com/owner/repo",
+            &scores,
+            &Weights::default(),
+            &registry,
+        );
+        PyScoreReport::new(report, &registry)
+    }
+
+    #[test]
+    fn report_attributes() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let report = Py::new(py, report()).unwrap();
+            let report = report.as_ref(py);
+            assert_eq!(
+                0.5,
+                report
+                    .getattr("bus_factor")
+                    .unwrap()
+                    .extract::<f64>()
+                    .unwrap()
+            );
+            assert!(report.getattr("license").unwrap().is_none());
+            assert!(report
+                .getattr("missing")
+                .unwrap_err()
+                .is_instance_of::<PyAttributeError>(py));
+            assert_eq!(
+                "no license found",
+                report
+                    .call_method1("explanation", ("license",))
+                    .unwrap()
+                    .extract::<String>()
+                    .unwrap()
+            );
+
+            let dict = report.call_method0("to_dict").unwrap();
+            assert_eq!(
+                "https://github.com/owner/repo",
+                dict.get_item("URL").unwrap().extract::<String>().unwrap()
+            );
+            assert!(dict.get_item("LICENSE_SCORE").unwrap().is_none());
+        });
+    }
+
+    #[test]
+    fn errors_raised() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let cases = [
+                (MetricError::Unsupported("x".to_string()), "InvalidUrl"),
+                (MetricError::NotFound("x".to_string()), "NotFound"),
+                (
+                    MetricError::RateLimited {
+                        resource: "core".to_string(),
+                        reset: None,
+                    },
+                    "RateLimited",
+                ),
+                (MetricError::Auth("x".to_string()), "MetricsError"),
+            ];
+            for (error, name) in cases {
+                let err = to_py_err(error);
+                assert_eq!(name, err.get_type(py).name().unwrap());
+                assert!(err.is_instance_of::<MetricsError>(py));
+            }
+            assert!(calcscore_py(py, "not a url", None, None)
+                .unwrap_err()
+                .is_instance_of::<InvalidUrl>(py));
+            assert!(
+                calcscore_py(py, "https://github.com/owner/repo", Some("missing"), None)
+                    .unwrap_err()
+                    .is_instance_of::<PyValueError>(py)
+            );
+        });
+    }
+
+    #[test]
+    fn errors_of_many() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let urls = vec![
+                "not a url".to_string(),
+                "https://gitlab.com/owner/repo".to_string(),
+            ];
+            let (reports, errors) = score_many(py, urls, Some(2), None, None).unwrap();
+            assert!(reports.is_empty());
+            assert_eq!(2, errors.len());
+            let error = errors["not a url"].as_ref(py);
+            assert!(error.is_instance_of::<InvalidUrl>().unwrap());
+            assert!(error.is_instance_of::<MetricsError>().unwrap());
+
+            assert!(score_many(py, vec![], None, Some("missing"), None)
+                .unwrap_err()
+                .is_instance_of::<PyValueError>(py));
+        });
+    }
+
+    #[test]
+    fn config_and_client() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let locals = [
+                ("Config", py.get_type::<PyConfig>()),
+                ("Client", py.get_type::<PyClient>()),
+                ("InvalidUrl", py.get_type::<InvalidUrl>()),
+            ]
+            .into_py_dict(py);
+            py.run(
+                r#"
+config = Config(
+    token="secret",
+    hosts=["GHE.example.com"],
+    weights={"license": 3, "bus_factor": 1},
+    curves={"bus_factor": {"type": "logistic", "midpoint": 5.0, "steepness": 1.0}},
+    cache_dir="/tmp/metrics-cache",
+    cache_ttl=60,
+    workers=2,
+)
+assert config.weights == {"license": 0.75, "bus_factor": 0.25}, config.weights
+assert config.curves["bus_factor"]["type"] == "logistic"
+assert config.hosts == ["ghe.example.com"]
+assert config.api_url == "https://ghe.example.com/api/v3"
+assert config.has_token and "secret" not in repr(config)
+assert (config.cache_dir, config.cache_ttl, config.workers) == ("/tmp/metrics-cache", 60, 2)
+
+for invalid in [{"weights": {"missing": 1}}, {"curves": {"bus_factor": {"type": "peak", "at": -1}}}]:
+    try:
+        Config(**invalid)
+        raise AssertionError(invalid)
+    except ValueError:
+        pass
+
+client = Client(config)
+assert client.config.workers == 2
+reports, errors = client.score_many(["not a url"])
+assert reports == [] and isinstance(errors["not a url"], InvalidUrl)
+try:
+    client.score("https://gitlab.com/owner/repo")
+    raise AssertionError()
+except InvalidUrl:
+    pass
+"#,
+                None,
+                Some(locals),
+            )
+            .unwrap();
+        });
+    }
+
+    #[test]
+    fn identity_of_client() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let stub = crate::http::tests::StubTransport::default().with(
+                "https://api.github.com/graphql",
+                200,
+                &crate::metrics::github::facts_response(serde_json::json!({
+                    "tags": { "edges": [{ "node": { "name": "v0.4.0" } }] }
+                })),
+            );
+            let config = PyConfig::from_env().unwrap();
+            let mut scoring = config.scoring.clone();
+            scoring.github = GithubConfig::for_host("github.com");
+            scoring.transport = Arc::new(stub);
+            let client = Py::new(py, PyClient { config, scoring }).unwrap();
+
+            let identity = client
+                .as_ref(py)
+                .call_method1("identity", ("https://github.com/owner/repo",))
+                .unwrap();
+            let attr = |name| identity.getattr(name).unwrap().extract::<String>().unwrap();
+            assert_eq!(
+                ("repo", "0.4.0", "tag"),
+                (
+                    attr("name").as_str(),
+                    attr("version").as_str(),
+                    attr("source").as_str()
+                )
+            );
+            assert!(package_identity(py, "not a url")
+                .unwrap_err()
+                .is_instance_of::<InvalidUrl>(py));
+        });
+    }
+
+    #[test]
+    fn check_score() {
+        // errors are formatted by the interpreter
+        pyo3::prepare_freethreaded_python();
+        let pulls: Vec<_> = (0..4)
+            .map(|i| serde_json::json!({ "node": { "reviews": { "totalCount": i % 2 } } }))
+            .collect();
+        let mut body: serde_json::Value =
+            serde_json::from_str(&crate::metrics::github::facts_response(serde_json::json!({
+                "issues": { "totalCount": 8 },
+                "closedIssues": { "totalCount": 6 },
+                "licenseInfo": { "spdxId": "MIT" },
+                "mentionableUsers": { "totalCount": 5 },
+                "pullRequests": { "edges": pulls },
+                "readme": { "text": "line\n".repeat(100) },
+                "tree": { "entries": [{ "name": "package.json", "type": "blob" }] },
+                "packageJson": { "text": "{\"dependencies\":{\"a\":\"~1.2.3\",\"b\":\"^1.2.3\"}}" }
+            })))
+            .unwrap();
+        body["data"]["search"]["issueCount"] = serde_json::json!(26);
+        let stub = crate::http::tests::StubTransport::default().with(
+            "https://api.github.com/graphql",
+            200,
+            &body.to_string(),
+        );
+        let mut config = ScoringConfig::from_env().unwrap();
+        config.github = GithubConfig::for_host("github.com");
+        config.transport = Arc::new(stub);
+        let report = Python::with_gil(|py| {
+            score_one(py, "https://github.com/owner/repo", &config)
+                .unwrap()
+                .report
+        });
+
+        let curves = Curves::default();
+        assert_eq!("https://github.com/owner/repo", report.url);
+        assert_eq!(
+            Some(curves.bus_factor.apply(5.0)),
+            report.score("BUS_FACTOR_SCORE")
+        );
+        assert_eq!(Some(1.0), report.score("LICENSE_SCORE"));
+        assert_eq!(Some(0.75), report.score("CORRECTNESS_SCORE"));
+        assert_eq!(Some(0.5), report.score("PINNING_PRACTICE_SCORE"));
+        assert_eq!(
+            Some(curves.ramp_up.apply(100.0)),
+            report.score("RAMP_UP_SCORE")
+        );
+        assert_eq!(
+            Some(curves.responsiveness.apply(26.0)),
+            report.score("RESPONSIVE_MAINTAINER_SCORE")
+        );
+        assert_eq!(Some(0.5), report.score("REVIEWED_CODE_SCORE"));
+        assert!((0.0..=1.0).contains(&report.net_score));
+        assert!(report.explanation.is_some());
+    }
+
+    // GitHub answering with the license the token may see, counting requests
+    #[derive(Debug)]
+    struct PerToken {
+        requests: Arc<AtomicUsize>,
+    }
+
+    impl Transport for PerToken {
+        fn send(&self, request: &HttpRequest) -> http::Result<HttpResponse> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            let license = match request.header_value("authorization") {
+                Some("Bearer one") => "MIT",
+                _ => "Apache-2.0",
+            };
+            Ok(HttpResponse {
+                status: 200,
+                headers: Vec::new(),
+                body: crate::metrics::github::facts_response(serde_json::json!({
+                    "licenseInfo": { "spdxId": license }
+                })),
+            })
+        }
+    }
+
+    #[test]
+    fn clients_share_cache_per_token() {
+        pyo3::prepare_freethreaded_python();
+        let dir = crate::http::tests::temp_dir("python_cache_tokens");
+        let requests = Arc::new(AtomicUsize::new(0));
+        let license = |token: &str| {
+            Python::with_gil(|py| {
+                let config = PyConfig::new(
+                    py,
+                    None,
+                    None,
+                    None,
+                    None,
+                    Some(token.to_string()),
+                    Some(vec!["github.com".to_string()]),
+                    None,
+                    None,
+                    Some(dir.clone()),
+                    None,
+                    None,
+                )
+                .unwrap();
+                // the cache of PyClient::new, in front of GitHub instead of the network
+                let (cache_dir, ttl) = config.cache.clone().unwrap();
+                let github = PerToken {
+                    requests: requests.clone(),
+                };
+                let mut scoring = config.scoring.clone();
+                scoring.transport =
+                    Arc::new(CachingTransport::new(Box::new(github), cache_dir, ttl));
+                let client = PyClient { config, scoring };
+                client
+                    .score(py, "https://github.com/owner/repo")
+                    .unwrap()
+                    .report
+                    .score("LICENSE_SCORE")
+            })
+        };
+
+        assert_eq!(Some(1.0), license("one"));
+        assert_eq!(Some(0.0), license("two"));
+        assert_eq!(Some(1.0), license("one"));
+        assert_eq!(2, requests.load(Ordering::SeqCst));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}